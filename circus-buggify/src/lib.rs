@@ -36,31 +36,272 @@
 //!     println!("buggified with a 100% probability!");
 //! }
 //!```
+//!
+//! ## Call sites
+//! Each use of `buggify` is identified by the `file:line` of its caller. A helper calling
+//! `buggify` on behalf of many callers is a single use, unless it is annotated with
+//! `#[track_caller]`, or it forwards the location of its caller to [`Buggifier::buggify_at`].
+//! ```rust
+//! use circus_buggify::Buggifier;
+//! use rand::rngs::SmallRng;
+//! use rand::SeedableRng;
+//! use std::panic::Location;
+//!
+//! #[track_caller]
+//! fn flaky_io(b: &Buggifier) -> bool {
+//!     b.buggify_at(Location::caller(), 1.0)
+//! }
+//!
+//! let b = Buggifier::new(SmallRng::seed_from_u64(42));
+//! // two uses, each firing once
+//! assert!(flaky_io(&b));
+//! assert!(flaky_io(&b));
+//! ```
+//!
+//! ## Features
+//! * `buggify` (enabled by default): the free functions, such as [`buggify`], consult the
+//!   global [`buggifier`]. When disabled, they always return `false` and are inlined to nothing,
+//!   so the sites left in production code cost nothing. A [`Buggifier`] is still working.
+//! * `serde`: a [`Buggifier`] can be serialized, with its seed and the outcome of every site
+//!   evaluated so far, to reproduce a run in another process.
 
 #![warn(missing_docs)]
 #![warn(rust_2018_idioms)]
 use parking_lot::Mutex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use once_cell::sync::Lazy;
 use rand::rngs::SmallRng;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
 use std::ops::Deref;
 use std::panic::Location;
 
+/// A probability, between 0 and 1 included
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Probability(f64);
+
+impl Probability {
+    /// creates a `Probability`, or `None` if `value` is not between 0 and 1
+    pub fn new(value: f64) -> Option<Probability> {
+        if (0.0..=1.0).contains(&value) {
+            Some(Probability(value))
+        } else {
+            None
+        }
+    }
+
+    /// creates a `Probability`, clamping `value` between 0 and 1. NaN is treated as 0
+    pub fn clamped(value: f64) -> Probability {
+        if value.is_nan() {
+            Probability(0.0)
+        } else {
+            Probability(value.clamp(0.0, 1.0))
+        }
+    }
+
+    /// returns the probability as a float
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+}
+
+/// Outcome of a `buggify` evaluation, telling why a fault was injected or not
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuggifyOutcome {
+    /// buggify is not enabled, as outside of a simulation
+    Disabled,
+    /// the site did not fire: it was not drawn, or it is pinned off for this run
+    NotActivated,
+    /// the site already fired during this run, and never fires twice
+    AlreadyFired,
+    /// the site fired, the fault should be injected
+    Fired,
+}
+
+impl BuggifyOutcome {
+    /// returns whether the fault should be injected
+    pub fn is_fired(&self) -> bool {
+        *self == BuggifyOutcome::Fired
+    }
+}
+
+/// How aggressively faults are injected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuggifyMode {
+    /// every site fires with its own probability
+    Normal,
+    /// every site fires with at least the probability `floor`, to maximize the exposure to
+    /// faults, in a nightly CI lane for instance. The outcomes are still given by the seed.
+    Chaos {
+        /// the minimum probability of every site
+        floor: f64,
+    },
+}
+
+impl BuggifyMode {
+    /// the floor of [`BuggifyMode::chaos`]
+    pub const CHAOS_FLOOR: f64 = 0.25;
+
+    /// [`BuggifyMode::Chaos`] with a floor of [`BuggifyMode::CHAOS_FLOOR`]
+    pub fn chaos() -> Self {
+        BuggifyMode::Chaos {
+            floor: Self::CHAOS_FLOOR,
+        }
+    }
+
+    /// Reads the mode from the `BUGGIFY_MODE` environment variable: `chaos`, optionally with
+    /// its floor like `chaos=0.5`, or `normal`. Defaults to [`BuggifyMode::Normal`].
+    /// Panics on any other value, so a misconfigured CI lane does not silently run normally.
+    pub fn from_env() -> Self {
+        match std::env::var("BUGGIFY_MODE") {
+            Ok(mode) => mode
+                .parse()
+                .unwrap_or_else(|error| panic!("invalid BUGGIFY_MODE {:?}: {}", mode, error)),
+            Err(_) => BuggifyMode::Normal,
+        }
+    }
+
+    /// returns whether the mode is [`BuggifyMode::Chaos`]
+    pub fn is_chaos(&self) -> bool {
+        matches!(self, BuggifyMode::Chaos { .. })
+    }
+
+    /// returns the minimum probability of every site
+    fn floor(&self) -> f64 {
+        match self {
+            BuggifyMode::Normal => 0.0,
+            BuggifyMode::Chaos { floor } => Probability::clamped(*floor).value(),
+        }
+    }
+}
+
+impl Default for BuggifyMode {
+    fn default() -> Self {
+        BuggifyMode::Normal
+    }
+}
+
+impl std::str::FromStr for BuggifyMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.split_once('=') {
+            None if mode == "normal" => Ok(BuggifyMode::Normal),
+            None if mode == "chaos" => Ok(BuggifyMode::chaos()),
+            Some(("chaos", floor)) => floor
+                .parse()
+                .map(|floor| BuggifyMode::Chaos { floor })
+                .map_err(|error| format!("invalid floor: {}", error)),
+            _ => Err(String::from(
+                "expected `normal`, `chaos` or `chaos=<floor>`",
+            )),
+        }
+    }
+}
+
+/// Chance of a site to fire
+enum Chance {
+    Probability(f64),
+    // never fires when the denominator is 0
+    Ratio(u32, u32),
+    // decided without drawing, and not raised by the chaos mode
+    Certain(bool),
+}
+
+impl Chance {
+    /// raises the chance to at least `floor`
+    fn with_floor(self, floor: f64) -> Chance {
+        match self {
+            Chance::Probability(probability) => Chance::Probability(probability.max(floor)),
+            Chance::Ratio(numerator, denominator)
+                if denominator != 0 && (numerator as f64) < floor * denominator as f64 =>
+            {
+                Chance::Probability(floor)
+            }
+            chance => chance,
+        }
+    }
+
+    fn draw(self, random: &mut SmallRng) -> bool {
+        match self {
+            Chance::Probability(probability) => random.gen_bool(probability),
+            Chance::Ratio(numerator, denominator) => {
+                denominator != 0 && random.gen_ratio(numerator.min(denominator), denominator)
+            }
+            Chance::Certain(fired) => fired,
+        }
+    }
+}
+
 /// Buggifier's definition
 #[derive(Debug)]
 pub struct Buggifier {
-    buggified_lines: Mutex<HashMap<String, bool>>,
-    random: Mutex<Option<SmallRng>>,
+    // a single lock is guarding the whole state, so there is no lock ordering to respect
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    // evaluated sites, and whether they fired. Ordered, so diagnostics are stable across runs
+    buggified_lines: BTreeMap<String, bool>,
+    // sites pinned off by the user, see `Buggifier::disable_line`
+    disabled_lines: BTreeSet<String>,
+    // groups of sites pinned off by the user, see `Buggifier::disable_group`
+    disabled_groups: BTreeSet<String>,
+    // groups with at least one site that fired since buggify has been enabled
+    activated_groups: BTreeSet<String>,
+    random: Option<SmallRng>,
+    // the seed of `random`, when it is known
+    seed: Option<u64>,
+    // number of evaluations of each site, see `Buggifier::buggify_on_nth`
+    site_evaluations: BTreeMap<String, u64>,
+    // number of evaluations since buggify has been enabled
+    evaluations: u64,
+    // number of evaluations that returned true
+    activations: u64,
+    // maximum number of activations, see `Buggifier::set_fault_budget`
+    budget: Option<u64>,
+    decisions: Decisions,
+    mode: BuggifyMode,
+}
+
+/// Where the decisions are coming from
+#[derive(Debug)]
+enum Decisions {
+    Live,
+    // every decision drawn is recorded
+    Recording(Vec<bool>),
+    // decisions are read from a recording, `requested` counts the decisions asked so far
+    Replaying {
+        recorded: Vec<bool>,
+        requested: usize,
+    },
+}
+
+impl Default for Decisions {
+    fn default() -> Self {
+        Decisions::Live
+    }
 }
 
 impl Buggifier {
     /// create a new Buggifier
     pub fn new(r: SmallRng) -> Self {
         Buggifier {
-            buggified_lines: Mutex::new(HashMap::new()),
-            random: Mutex::new(Some(r)),
+            inner: Mutex::new(Inner {
+                buggified_lines: BTreeMap::new(),
+                disabled_lines: BTreeSet::new(),
+                disabled_groups: BTreeSet::new(),
+                activated_groups: BTreeSet::new(),
+                random: Some(r),
+                seed: None,
+                site_evaluations: BTreeMap::new(),
+                evaluations: 0,
+                activations: 0,
+                budget: None,
+                decisions: Decisions::Live,
+                mode: BuggifyMode::Normal,
+            }),
         }
     }
 
@@ -71,7 +312,9 @@ impl Buggifier {
         self.handle_buggify(format!("{}:{}", location.file(), location.line()), 0.05)
     }
 
-    /// `buggify` version where you can choose the probability.
+    #[track_caller]
+    /// `buggify` version where you can choose the probability,
+    /// which is clamped between 0 and 1.
     pub fn buggify_with_prob(&self, probability: f64) -> bool {
         let location = Location::caller();
         self.handle_buggify(
@@ -80,41 +323,336 @@ impl Buggifier {
         )
     }
 
+    /// `buggify` version where the call site is given, so a helper shared by many callers can
+    /// forward the location of its own caller, see [`Location::caller`].
+    /// The probability is clamped between 0 and 1.
+    pub fn buggify_at(&self, location: &Location<'_>, probability: f64) -> bool {
+        self.handle_buggify(
+            format!("{}:{}", location.file(), location.line()),
+            probability,
+        )
+    }
+
+    #[track_caller]
+    /// `buggify` version where you can choose the probability.
+    pub fn buggify_with_probability(&self, probability: Probability) -> bool {
+        let location = Location::caller();
+        self.handle_buggify(
+            format!("{}:{}", location.file(), location.line()),
+            probability.value(),
+        )
+    }
+
+    #[track_caller]
+    /// `buggify` version returning why the fault was injected or not, instead of a `bool`.
+    /// The probability is clamped between 0 and 1.
+    pub fn buggify_outcome(&self, probability: f64) -> BuggifyOutcome {
+        let location = Location::caller();
+        let probability = Probability::clamped(probability);
+        self.handle_outcome_with(
+            format!("{}:{}", location.file(), location.line()),
+            None,
+            |_, _| Chance::Probability(probability.value()),
+        )
+    }
+
+    #[track_caller]
+    /// `buggify` version firing exactly on the `n`th evaluation of the site since buggify has
+    /// been enabled, counting from 1, to inject a fault at a precise point without searching
+    /// for a seed. It is not drawing from the random source, so the other sites are not affected.
+    /// Disabled sites, the fault budget and the recorded decisions apply as for the other versions.
+    pub fn buggify_on_nth(&self, n: u64) -> bool {
+        let location = Location::caller();
+        self.handle_buggify_nth(format!("{}:{}", location.file(), location.line()), n)
+    }
+
+    #[track_caller]
+    /// `buggify` version where the probability is decaying as buggify is evaluated,
+    /// to model a system that is more fragile at startup. The probability starts at `initial`,
+    /// and is halved every `half_life` evaluations of any site.
+    pub fn buggify_decaying(&self, initial: f64, half_life: u64) -> bool {
+        let location = Location::caller();
+        self.handle_buggify_with(
+            format!("{}:{}", location.file(), location.line()),
+            None,
+            |evaluations, _| {
+                let half_lives = evaluations as f64 / half_life.max(1) as f64;
+                Chance::Probability(
+                    Probability::clamped(initial * 0.5_f64.powf(half_lives)).value(),
+                )
+            },
+        )
+    }
+
+    #[track_caller]
+    /// `buggify` version where the probability is given as `numerator` in `denominator`,
+    /// avoiding float imprecision for rare faults. Never fires when `denominator` is 0.
+    pub fn buggify_with_ratio(&self, numerator: u32, denominator: u32) -> bool {
+        let location = Location::caller();
+        self.handle_buggify_ratio(
+            format!("{}:{}", location.file(), location.line()),
+            numerator,
+            denominator,
+        )
+    }
+
+    #[track_caller]
+    /// `buggify` version where the site belongs to a `group`, so a whole subsystem can be
+    /// toggled with [`Buggifier::disable_group`]. The probability is clamped between 0 and 1.
+    pub fn buggify_in_group(&self, group: &str, probability: f64) -> bool {
+        let location = Location::caller();
+        let probability = Probability::clamped(probability);
+        self.handle_buggify_with(
+            format!("{}:{}", location.file(), location.line()),
+            Some(group),
+            |_, _| Chance::Probability(probability.value()),
+        )
+    }
+
     fn handle_buggify(&self, line: String, probability: f64) -> bool {
-        let mut lock = self.random.lock();
+        let probability = Probability::clamped(probability);
+        self.handle_buggify_with(line, None, |_, _| Chance::Probability(probability.value()))
+    }
+
+    fn handle_buggify_ratio(&self, line: String, numerator: u32, denominator: u32) -> bool {
+        self.handle_buggify_with(line, None, |_, _| Chance::Ratio(numerator, denominator))
+    }
+
+    fn handle_buggify_with<D>(&self, line: String, group: Option<&str>, chance: D) -> bool
+    where
+        D: FnOnce(u64, u64) -> Chance,
+    {
+        self.handle_outcome_with(line, group, chance).is_fired()
+    }
+
+    fn handle_buggify_nth(&self, line: String, n: u64) -> bool {
+        self.handle_buggify_with(line, None, |_, site_evaluations| {
+            Chance::Certain(site_evaluations == n)
+        })
+    }
+
+    /// `chance` returns the chance to fire, given the number of previous evaluations of any site,
+    /// and the number of evaluations of this site, including this one
+    fn handle_outcome_with<D>(&self, line: String, group: Option<&str>, chance: D) -> BuggifyOutcome
+    where
+        D: FnOnce(u64, u64) -> Chance,
+    {
+        let mut lock = self.inner.lock();
+        let Inner {
+            buggified_lines,
+            disabled_lines,
+            disabled_groups,
+            activated_groups,
+            random,
+            evaluations,
+            activations,
+            budget,
+            decisions,
+            mode,
+            site_evaluations,
+            ..
+        } = &mut *lock;
 
-        match (*lock).as_mut() {
-            None => false,
+        match random.as_mut() {
+            None => BuggifyOutcome::Disabled,
             Some(deterministic_random) => {
-                let mut already_buggified = self.buggified_lines.lock();
-                if !already_buggified.contains_key(&line)
-                    && deterministic_random.gen_bool(probability)
-                {
-                    already_buggified.insert(line, true);
-                    return true;
+                let previous_evaluations = *evaluations;
+                *evaluations += 1;
+                let site_evaluation = site_evaluations.entry(line.clone()).or_insert(0);
+                *site_evaluation += 1;
+                if buggified_lines.get(&line) == Some(&true) {
+                    return BuggifyOutcome::AlreadyFired;
+                }
+                let drawn = match decisions {
+                    // once the recording is exhausted, no site fires anymore
+                    Decisions::Replaying {
+                        recorded,
+                        requested,
+                    } => {
+                        *requested += 1;
+                        recorded.get(*requested - 1).copied().unwrap_or(false)
+                    }
+                    _ => chance(previous_evaluations, *site_evaluation)
+                        .with_floor(mode.floor())
+                        .draw(deterministic_random),
+                };
+                if let Decisions::Recording(recorded) = decisions {
+                    recorded.push(drawn);
+                }
+                // a disabled site is still drawing, so the other sites keep the same outcomes
+                let disabled = disabled_lines.contains(&line)
+                    || group.map_or(false, |group| disabled_groups.contains(group))
+                    || budget.map_or(false, |budget| *activations >= budget);
+                let fired = drawn && !disabled;
+                if let (true, Some(group)) = (fired, group) {
+                    activated_groups.insert(group.to_string());
+                }
+                buggified_lines.insert(line, fired);
+                *activations += fired as u64;
+                if fired {
+                    BuggifyOutcome::Fired
+                } else {
+                    BuggifyOutcome::NotActivated
                 }
-                false
             }
         }
     }
 
     /// checks if buggify is enabled
     pub fn is_buggify_enabled(&self) -> bool {
-        self.random.lock().is_some()
+        self.inner.lock().random.is_some()
     }
 
     /// enables buggify by giving a random source
     pub fn enable_buggify(&self, r: SmallRng) {
-        let mut data = self.random.lock();
-        *data = Some(r);
+        let mut inner = self.inner.lock();
+        inner.random = Some(r);
+        inner.seed = None;
+    }
+
+    /// enables buggify with a random source seeded by `seed`
+    pub fn enable_buggify_with_seed(&self, seed: u64) {
+        let mut inner = self.inner.lock();
+        inner.random = Some(SmallRng::seed_from_u64(seed));
+        inner.seed = Some(seed);
+    }
+
+    /// returns the sites, formatted as `file:line`, that fired since buggify has been enabled, sorted
+    pub fn activated_lines(&self) -> Vec<String> {
+        self.inner
+            .lock()
+            .buggified_lines
+            .iter()
+            .filter(|(_, fired)| **fired)
+            .map(|(line, _)| line.clone())
+            .collect()
+    }
+
+    /// returns the groups with at least one site that fired since buggify has been enabled, sorted
+    pub fn activated_groups(&self) -> Vec<String> {
+        self.inner.lock().activated_groups.iter().cloned().collect()
+    }
+
+    /// panics unless a fault labelled `label` was injected since buggify has been enabled,
+    /// the label being either a group or a site formatted as `file:line`. A test can then
+    /// make sure its seed exercised the failure path it is about, instead of passing silently.
+    #[track_caller]
+    pub fn assert_injected(&self, label: &str) {
+        let inner = self.inner.lock();
+        let injected = inner.activated_groups.contains(label)
+            || inner.buggified_lines.get(label) == Some(&true);
+        if !injected {
+            let groups: Vec<String> = inner.activated_groups.iter().cloned().collect();
+            drop(inner);
+            panic!(
+                "fault {:?} was never injected, injected groups: {:?}",
+                label, groups
+            );
+        }
+    }
+
+    /// returns whether the site `key`, formatted as `file:line`, fired since buggify has been
+    /// enabled, or `None` if it has not been evaluated yet. This is not evaluating the site.
+    pub fn peek(&self, key: &str) -> Option<bool> {
+        self.inner.lock().buggified_lines.get(key).copied()
+    }
+
+    /// sets how aggressively faults are injected, see [`BuggifyMode`].
+    /// The mode is kept when buggify is disabled.
+    pub fn set_mode(&self, mode: BuggifyMode) {
+        self.inner.lock().mode = mode;
+    }
+
+    /// returns how aggressively faults are injected
+    pub fn mode(&self) -> BuggifyMode {
+        self.inner.lock().mode
+    }
+
+    /// pins the site `key`, formatted as `file:line`, so it never fires, while every other
+    /// site stays active. Disabled sites are kept when buggify is disabled.
+    pub fn disable_line(&self, key: &str) {
+        self.inner.lock().disabled_lines.insert(key.to_string());
+    }
+
+    /// re-enables a site disabled by [`Buggifier::disable_line`]
+    pub fn enable_line(&self, key: &str) {
+        self.inner.lock().disabled_lines.remove(key);
+    }
+
+    /// pins every site of `group`, see [`Buggifier::buggify_in_group`], so they never fire,
+    /// while the sites of other groups stay active. Disabled groups are kept when buggify is disabled.
+    pub fn disable_group(&self, group: &str) {
+        self.inner.lock().disabled_groups.insert(group.to_string());
+    }
+
+    /// re-enables a group disabled by [`Buggifier::disable_group`]
+    pub fn enable_group(&self, group: &str) {
+        self.inner.lock().disabled_groups.remove(group);
     }
 
     /// disable buggify
     pub fn disable_buggify(&self) {
-        let mut data = self.random.lock();
-        *data = None;
-        let mut map = self.buggified_lines.lock();
-        map.clear();
+        let mut lock = self.inner.lock();
+        lock.random = None;
+        lock.seed = None;
+        lock.buggified_lines.clear();
+        lock.activated_groups.clear();
+        lock.site_evaluations.clear();
+        lock.evaluations = 0;
+        lock.activations = 0;
+        lock.decisions = Decisions::Live;
+    }
+
+    /// stops firing once buggify returned true `budget` times since it has been enabled,
+    /// see [`Buggifier::activation_count`]. Sites keep drawing, so the outcomes within
+    /// the budget do not change. `None` removes the budget, which is kept when buggify is disabled.
+    pub fn set_fault_budget(&self, budget: Option<u64>) {
+        self.inner.lock().budget = budget;
+    }
+
+    /// returns the number of times buggify has been evaluated since it has been enabled
+    pub fn evaluation_count(&self) -> u64 {
+        self.inner.lock().evaluations
+    }
+
+    /// returns the number of times buggify returned true since it has been enabled
+    pub fn activation_count(&self) -> u64 {
+        self.inner.lock().activations
+    }
+
+    /// starts recording every decision drawn, see [`Buggifier::recorded_decisions`]
+    pub fn record_decisions(&self) {
+        self.inner.lock().decisions = Decisions::Recording(vec![]);
+    }
+
+    /// returns the decisions drawn since [`Buggifier::record_decisions`],
+    /// or `None` if the buggifier is not recording
+    pub fn recorded_decisions(&self) -> Option<Vec<bool>> {
+        match &self.inner.lock().decisions {
+            Decisions::Recording(recorded) => Some(recorded.clone()),
+            _ => None,
+        }
+    }
+
+    /// replays previously recorded decisions in order, instead of drawing them.
+    /// Once every decision has been replayed, no site fires anymore.
+    pub fn replay_decisions(&self, decisions: Vec<bool>) {
+        self.inner.lock().decisions = Decisions::Replaying {
+            recorded: decisions,
+            requested: 0,
+        };
+    }
+
+    /// returns how many decisions were recorded, and how many were asked so far while
+    /// replaying them, or `None` if the buggifier is not replaying
+    pub fn replay_progress(&self) -> Option<(usize, usize)> {
+        match &self.inner.lock().decisions {
+            Decisions::Replaying {
+                recorded,
+                requested,
+            } => Some((recorded.len(), *requested)),
+            _ => None,
+        }
     }
 }
 
@@ -122,9 +660,47 @@ impl Default for Buggifier {
     /// Create a new Buggifier
     fn default() -> Self {
         Buggifier {
-            buggified_lines: Mutex::new(HashMap::new()),
-            random: Mutex::new(None),
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+}
+
+/// What is shipped when serializing a [`Buggifier`]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Snapshot {
+    seed: Option<u64>,
+    buggified_lines: BTreeMap<String, bool>,
+}
+
+/// Serializes the seed given to [`Buggifier::enable_buggify_with_seed`], if any,
+/// and the outcome of every site evaluated so far.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Buggifier {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let inner = self.inner.lock();
+        let snapshot = Snapshot {
+            seed: inner.seed,
+            buggified_lines: inner.buggified_lines.clone(),
+        };
+        drop(inner);
+        snapshot.serialize(serializer)
+    }
+}
+
+/// Deserializes a buggifier where the serialized sites are already decided: the sites that
+/// fired never fire again. Buggify is enabled with the serialized seed, if any, drawing the
+/// outcomes of the other sites from the start of its sequence.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Buggifier {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let snapshot = Snapshot::deserialize(deserializer)?;
+        let buggifier = Buggifier::default();
+        if let Some(seed) = snapshot.seed {
+            buggifier.enable_buggify_with_seed(seed);
         }
+        buggifier.inner.lock().buggified_lines = snapshot.buggified_lines;
+        Ok(buggifier)
     }
 }
 
@@ -137,6 +713,7 @@ pub fn buggifier() -> &'static Buggifier {
     BUGGIFIER_INSTANCE.deref()
 }
 
+#[cfg(feature = "buggify")]
 #[track_caller]
 /// `buggify` will returns true only once per execution with a probability of 0.05.
 pub fn buggify() -> bool {
@@ -144,8 +721,10 @@ pub fn buggify() -> bool {
     buggifier().handle_buggify(format!("{}:{}", location.file(), location.line()), 0.05)
 }
 
+#[cfg(feature = "buggify")]
 #[track_caller]
-/// `buggify` version where you can choose the probability.
+/// `buggify` version where you can choose the probability,
+/// which is clamped between 0 and 1.
 pub fn buggify_with_prob(probability: f64) -> bool {
     let location = Location::caller();
     buggifier().handle_buggify(
@@ -154,6 +733,133 @@ pub fn buggify_with_prob(probability: f64) -> bool {
     )
 }
 
+#[cfg(feature = "buggify")]
+#[track_caller]
+/// `buggify` version where you can choose the probability.
+pub fn buggify_with_probability(probability: Probability) -> bool {
+    let location = Location::caller();
+    buggifier().handle_buggify(
+        format!("{}:{}", location.file(), location.line()),
+        probability.value(),
+    )
+}
+
+#[cfg(feature = "buggify")]
+#[track_caller]
+/// `buggify` version returning why the fault was injected or not, see
+/// [`Buggifier::buggify_outcome`].
+pub fn buggify_outcome(probability: f64) -> BuggifyOutcome {
+    let location = Location::caller();
+    let probability = Probability::clamped(probability);
+    buggifier().handle_outcome_with(
+        format!("{}:{}", location.file(), location.line()),
+        None,
+        |_, _| Chance::Probability(probability.value()),
+    )
+}
+
+#[cfg(feature = "buggify")]
+#[track_caller]
+/// `buggify` version firing exactly on the `n`th evaluation of the site, see
+/// [`Buggifier::buggify_on_nth`].
+pub fn buggify_on_nth(n: u64) -> bool {
+    let location = Location::caller();
+    buggifier().handle_buggify_nth(format!("{}:{}", location.file(), location.line()), n)
+}
+
+#[cfg(feature = "buggify")]
+#[track_caller]
+/// `buggify` version where the probability is given as `numerator` in `denominator`.
+pub fn buggify_with_ratio(numerator: u32, denominator: u32) -> bool {
+    let location = Location::caller();
+    buggifier().handle_buggify_ratio(
+        format!("{}:{}", location.file(), location.line()),
+        numerator,
+        denominator,
+    )
+}
+
+#[cfg(feature = "buggify")]
+#[track_caller]
+/// `buggify` version where the site belongs to a `group`, see [`Buggifier::buggify_in_group`].
+pub fn buggify_in_group(group: &str, probability: f64) -> bool {
+    let location = Location::caller();
+    let probability = Probability::clamped(probability);
+    buggifier().handle_buggify_with(
+        format!("{}:{}", location.file(), location.line()),
+        Some(group),
+        |_, _| Chance::Probability(probability.value()),
+    )
+}
+
+#[cfg(feature = "buggify")]
+/// `buggify` version where the call site is given, see [`Buggifier::buggify_at`].
+pub fn buggify_at(location: &Location<'_>, probability: f64) -> bool {
+    buggifier().buggify_at(location, probability)
+}
+
+// Without the `buggify` feature, the sites below are compiled out: they never reach the global
+// buggifier, and always return false.
+
+#[cfg(not(feature = "buggify"))]
+#[inline(always)]
+/// `buggify` will returns true only once per execution with a probability of 0.05.
+pub fn buggify() -> bool {
+    false
+}
+
+#[cfg(not(feature = "buggify"))]
+#[inline(always)]
+/// `buggify` version where you can choose the probability,
+/// which is clamped between 0 and 1.
+pub fn buggify_with_prob(_probability: f64) -> bool {
+    false
+}
+
+#[cfg(not(feature = "buggify"))]
+#[inline(always)]
+/// `buggify` version where you can choose the probability.
+pub fn buggify_with_probability(_probability: Probability) -> bool {
+    false
+}
+
+#[cfg(not(feature = "buggify"))]
+#[inline(always)]
+/// `buggify` version returning why the fault was injected or not, see
+/// [`Buggifier::buggify_outcome`].
+pub fn buggify_outcome(_probability: f64) -> BuggifyOutcome {
+    BuggifyOutcome::Disabled
+}
+
+#[cfg(not(feature = "buggify"))]
+#[inline(always)]
+/// `buggify` version firing exactly on the `n`th evaluation of the site, see
+/// [`Buggifier::buggify_on_nth`].
+pub fn buggify_on_nth(_n: u64) -> bool {
+    false
+}
+
+#[cfg(not(feature = "buggify"))]
+#[inline(always)]
+/// `buggify` version where the probability is given as `numerator` in `denominator`.
+pub fn buggify_with_ratio(_numerator: u32, _denominator: u32) -> bool {
+    false
+}
+
+#[cfg(not(feature = "buggify"))]
+#[inline(always)]
+/// `buggify` version where the site belongs to a `group`, see [`Buggifier::buggify_in_group`].
+pub fn buggify_in_group(_group: &str, _probability: f64) -> bool {
+    false
+}
+
+#[cfg(not(feature = "buggify"))]
+#[inline(always)]
+/// `buggify` version where the call site is given, see [`Buggifier::buggify_at`].
+pub fn buggify_at(_location: &Location<'_>, _probability: f64) -> bool {
+    false
+}
+
 /// checks if buggify is enabled
 pub fn is_buggify_enabled() -> bool {
     buggifier().is_buggify_enabled()
@@ -164,6 +870,11 @@ pub fn enable_buggify(r: SmallRng) {
     buggifier().enable_buggify(r)
 }
 
+/// enables buggify with a random source seeded by `seed`
+pub fn enable_buggify_with_seed(seed: u64) {
+    buggifier().enable_buggify_with_seed(seed)
+}
+
 /// disable buggify
 pub fn disable_buggify() {
     buggifier().disable_buggify()
@@ -173,10 +884,13 @@ pub fn disable_buggify() {
 mod tests {
     use crate::{
         buggifier, buggify, buggify_with_prob, disable_buggify, enable_buggify, is_buggify_enabled,
-        Buggifier,
+        Buggifier, BuggifyMode, BuggifyOutcome, Probability,
     };
     use rand::rngs::SmallRng;
     use rand::SeedableRng;
+    use std::panic::{AssertUnwindSafe, Location};
+    use std::sync::Arc;
+    use std::thread;
     use tracing::Level;
 
     #[test]
@@ -191,11 +905,9 @@ mod tests {
         assert!(!b.buggify_with_prob(1.0), "should not buggified");
 
         {
-            let data = b.random.lock();
-            assert!((*data).is_none());
-
-            let map = b.buggified_lines.lock();
-            assert!((*map).is_empty());
+            let lock = b.inner.lock();
+            assert!(lock.random.is_none());
+            assert!(lock.buggified_lines.is_empty());
         }
 
         let random = SmallRng::seed_from_u64(42);
@@ -214,15 +926,14 @@ mod tests {
         }
 
         {
-            let data = b.random.lock();
-            assert!((*data).is_some());
+            let lock = b.inner.lock();
+            assert!(lock.random.is_some());
 
-            let map = b.buggified_lines.lock();
-            assert_eq!((*map).len(), 1);
-            for key in (*map).keys() {
+            assert_eq!(lock.buggified_lines.len(), 1);
+            for key in lock.buggified_lines.keys() {
                 assert!(key.starts_with(&file!().to_string()));
             }
-            for value in (*map).values() {
+            for value in lock.buggified_lines.values() {
                 assert!(value);
             }
         }
@@ -232,6 +943,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "buggify")]
     fn test_static_buggify() {
         let _ = tracing_subscriber::fmt()
             .with_max_level(Level::TRACE)
@@ -258,15 +970,14 @@ mod tests {
             );
         }
         {
-            let data = buggifier().random.lock();
-            assert!((*data).is_some());
+            let lock = buggifier().inner.lock();
+            assert!(lock.random.is_some());
 
-            let map = buggifier().buggified_lines.lock();
-            assert_eq!((*map).len(), 1);
-            for key in (*map).keys() {
+            assert_eq!(lock.buggified_lines.len(), 1);
+            for key in lock.buggified_lines.keys() {
                 assert!(key.starts_with(&file!().to_string()));
             }
-            for value in (*map).values() {
+            for value in lock.buggified_lines.values() {
                 assert!(value);
             }
         }
@@ -274,4 +985,400 @@ mod tests {
         buggifier().disable_buggify();
         assert!(!buggifier().buggify_with_prob(1.0), "should not buggified");
     }
+
+    #[test]
+    fn test_concurrent_buggify() {
+        let b = Arc::new(Buggifier::new(SmallRng::seed_from_u64(42)));
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let b = b.clone();
+                thread::spawn(move || {
+                    for j in 0..1000 {
+                        if i % 2 == 0 {
+                            b.buggify_with_prob(0.5);
+                        } else if j % 10 == 0 {
+                            b.disable_buggify();
+                            b.enable_buggify(SmallRng::seed_from_u64(j));
+                        } else {
+                            b.is_buggify_enabled();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().expect("thread panicked");
+        }
+        assert!(b.is_buggify_enabled());
+    }
+
+    #[test]
+    fn test_buggify_decaying() {
+        let run = |seed| {
+            let b = Buggifier::new(SmallRng::seed_from_u64(seed));
+            let early = b.buggify_decaying(0.5, 10);
+            for _ in 0..100 {
+                b.buggify_with_prob(0.0);
+            }
+            let late = b.buggify_decaying(0.5, 10);
+            (early, late)
+        };
+
+        let (mut early, mut late) = (0, 0);
+        for seed in 0..1000 {
+            let result = run(seed);
+            assert_eq!(result, run(seed), "seed {} is not reproducible", seed);
+            early += result.0 as usize;
+            late += result.1 as usize;
+        }
+        assert!(early > 400, "early sites fired {} times", early);
+        assert!(late < early / 10, "late sites fired {} times", late);
+    }
+
+    #[test]
+    fn test_activated_lines() {
+        let b = Buggifier::new(SmallRng::seed_from_u64(42));
+        assert!(b.activated_lines().is_empty());
+
+        assert!(b.buggify_with_prob(1.0));
+        assert!(b.buggify_with_prob(1.0));
+        assert!(!b.buggify_with_prob(0.0));
+
+        let lines = b.activated_lines();
+        assert_eq!(lines.len(), 2);
+        let mut sorted = lines.clone();
+        sorted.sort();
+        assert_eq!(lines, sorted);
+        assert_eq!(lines, b.activated_lines());
+
+        b.disable_buggify();
+        assert!(b.activated_lines().is_empty());
+    }
+
+    #[test]
+    fn test_probability() {
+        assert_eq!(Probability::new(0.5).map(|p| p.value()), Some(0.5));
+        assert!(Probability::new(0.0).is_some());
+        assert!(Probability::new(1.0).is_some());
+        assert!(Probability::new(1.5).is_none());
+        assert!(Probability::new(-0.1).is_none());
+        assert!(Probability::new(f64::NAN).is_none());
+        assert_eq!(Probability::clamped(2.0).value(), 1.0);
+        assert_eq!(Probability::clamped(f64::NAN).value(), 0.0);
+
+        let b = Buggifier::new(SmallRng::seed_from_u64(42));
+        let always = Probability::new(1.0).unwrap();
+        assert!(b.buggify_with_probability(always));
+        assert!(!b.buggify_with_probability(Probability::new(0.0).unwrap()));
+        // out of range probabilities are clamped instead of panicking
+        assert!(b.buggify_with_prob(42.0));
+        assert!(!b.buggify_with_prob(-1.0));
+    }
+
+    #[test]
+    fn test_record_and_replay_decisions() {
+        let recording = Buggifier::new(SmallRng::seed_from_u64(42));
+        recording.record_decisions();
+        let fired: Vec<bool> = (0..20).map(|_| recording.buggify_with_prob(0.1)).collect();
+        let decisions = recording.recorded_decisions().expect("should be recording");
+        assert_eq!(decisions, fired[..decisions.len()]);
+        let recorded = decisions.len();
+
+        // the seed does not matter when replaying
+        let replaying = Buggifier::new(SmallRng::seed_from_u64(1337));
+        replaying.replay_decisions(decisions);
+        let replayed: Vec<bool> = (0..20).map(|_| replaying.buggify_with_prob(0.1)).collect();
+        assert_eq!(replayed, fired);
+        assert!(replaying.recorded_decisions().is_none());
+        assert_eq!(replaying.replay_progress(), Some((recorded, recorded)));
+        assert!(recording.replay_progress().is_none());
+    }
+
+    #[test]
+    fn test_counters() {
+        let b = Buggifier::new(SmallRng::seed_from_u64(42));
+        for _ in 0..100 {
+            b.buggify();
+        }
+        // seed 42 fires on the 9th evaluation
+        assert_eq!(b.evaluation_count(), 100);
+        assert_eq!(b.activation_count(), 1);
+
+        for _ in 0..3 {
+            b.buggify_with_prob(1.0);
+        }
+        assert_eq!(b.evaluation_count(), 103);
+        assert_eq!(b.activation_count(), 2);
+
+        b.disable_buggify();
+        assert_eq!(b.evaluation_count(), 0);
+        assert_eq!(b.activation_count(), 0);
+    }
+
+    #[test]
+    fn test_peek() {
+        let b = Buggifier::new(SmallRng::seed_from_u64(42));
+        let key = |line: u32| format!("{}:{}", file!(), line);
+
+        let fired_line = line!() + 1;
+        let fired = b.buggify_with_prob(1.0);
+        let missed_line = line!() + 1;
+        let missed = b.buggify_with_prob(0.0);
+
+        assert_eq!(b.peek(&key(fired_line)), Some(fired));
+        assert_eq!(b.peek(&key(missed_line)), Some(missed));
+        assert_eq!(b.peek(&key(line!())), None);
+        // peeking is not evaluating the site
+        assert_eq!(b.peek(&key(fired_line)), Some(true));
+        assert_eq!(b.activated_lines(), vec![key(fired_line)]);
+    }
+
+    #[test]
+    fn test_disable_line() {
+        let b = Buggifier::new(SmallRng::seed_from_u64(42));
+        let disabled_line = line!() + 4;
+        b.disable_line(&format!("{}:{}", file!(), disabled_line));
+
+        for _ in 0..10 {
+            assert!(!b.buggify_with_prob(1.0), "disabled site should never fire");
+        }
+        assert!(b.buggify_with_prob(1.0), "other sites should still fire");
+
+        b.enable_line(&format!("{}:{}", file!(), disabled_line));
+        let fired = (0..10).any(|_| b.buggify_with_prob(1.0));
+        assert!(fired);
+    }
+
+    /// a helper shared by many callers, forwarding their location
+    fn forwarding_helper(b: &Buggifier, location: &Location<'_>) -> bool {
+        b.buggify_at(location, 1.0)
+    }
+
+    /// a helper shared by many callers, not forwarding their location
+    fn collapsing_helper(b: &Buggifier) -> bool {
+        b.buggify_with_prob(1.0)
+    }
+
+    #[test]
+    fn test_buggify_at() {
+        let b = Buggifier::new(SmallRng::seed_from_u64(42));
+        assert!(forwarding_helper(&b, Location::caller()));
+        assert!(forwarding_helper(&b, Location::caller()));
+        // the same caller is the same site
+        let location = Location::caller();
+        assert!(forwarding_helper(&b, location));
+        assert!(!forwarding_helper(&b, location));
+
+        assert!(collapsing_helper(&b));
+        assert!(
+            !collapsing_helper(&b),
+            "both callers share the helper's site"
+        );
+    }
+
+    #[test]
+    fn test_fault_budget() {
+        let b = Buggifier::new(SmallRng::seed_from_u64(42));
+        b.set_fault_budget(Some(1));
+        assert!(b.buggify_with_prob(1.0));
+        assert!(!b.buggify_with_prob(1.0), "the budget is spent");
+        assert!(!b.buggify_with_prob(1.0), "the budget is spent");
+        assert_eq!(b.activation_count(), 1);
+
+        b.set_fault_budget(None);
+        assert!(b.buggify_with_prob(1.0));
+    }
+
+    #[test]
+    fn test_groups() {
+        let b = Buggifier::new(SmallRng::seed_from_u64(42));
+        b.disable_group("network");
+
+        for _ in 0..10 {
+            assert!(
+                !b.buggify_in_group("network", 1.0),
+                "disabled group should never fire"
+            );
+            assert!(
+                !b.buggify_in_group("network", 1.0),
+                "disabled group should never fire"
+            );
+        }
+        assert!(
+            b.buggify_in_group("disk", 1.0),
+            "other groups should still fire"
+        );
+        assert!(
+            b.buggify_with_prob(1.0),
+            "ungrouped sites should still fire"
+        );
+
+        b.enable_group("network");
+        assert!(b.buggify_in_group("network", 1.0));
+    }
+
+    #[test]
+    fn test_assert_injected() {
+        let b = Buggifier::new(SmallRng::seed_from_u64(42));
+        assert!(b.buggify_in_group("disk", 1.0));
+        assert!(!b.buggify_in_group("network", 0.0));
+        let site = format!("{}:{}", file!(), line!() + 1);
+        assert!(b.buggify_with_prob(1.0));
+
+        assert_eq!(b.activated_groups(), vec!["disk".to_string()]);
+        b.assert_injected("disk");
+        b.assert_injected(&site);
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| b.assert_injected("network")));
+        assert!(result.is_err(), "the network group never fired");
+
+        b.disable_buggify();
+        assert!(b.activated_groups().is_empty());
+    }
+
+    #[test]
+    fn test_buggify_with_ratio() {
+        let b = Buggifier::new(SmallRng::seed_from_u64(42));
+        assert!(!b.buggify_with_ratio(1, 0));
+        assert!(!b.buggify_with_ratio(0, 1000));
+        assert!(b.buggify_with_ratio(1, 1));
+        assert!(b.buggify_with_ratio(2, 1));
+
+        let fired = (0..1000)
+            .filter(|seed| Buggifier::new(SmallRng::seed_from_u64(*seed)).buggify_with_ratio(1, 10))
+            .count();
+        assert!(fired > 50 && fired < 150, "fired {} times", fired);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        // a single site, evaluated by both buggifiers
+        fn always(b: &Buggifier) -> bool {
+            b.buggify_with_prob(1.0)
+        }
+
+        let b = Buggifier::default();
+        b.enable_buggify_with_seed(42);
+        assert!(always(&b));
+        let spared = format!("{}:{}", file!(), line!() + 1);
+        assert!(!b.buggify_with_prob(0.0));
+
+        let json = serde_json::to_string(&b).unwrap();
+        let other: Buggifier = serde_json::from_str(&json).unwrap();
+        assert!(other.is_buggify_enabled());
+        assert_eq!(other.activated_lines(), b.activated_lines());
+        assert_eq!(other.peek(&spared), Some(false));
+        assert!(!always(&other), "the site already fired");
+        assert_eq!(serde_json::to_string(&other).unwrap(), json);
+    }
+
+    #[test]
+    fn test_buggify_outcome() {
+        let b = Buggifier::default();
+        assert_eq!(b.buggify_outcome(1.0), BuggifyOutcome::Disabled);
+
+        b.enable_buggify_with_seed(42);
+        let outcomes: Vec<BuggifyOutcome> = (0..2).map(|_| b.buggify_outcome(1.0)).collect();
+        assert_eq!(
+            outcomes,
+            vec![BuggifyOutcome::Fired, BuggifyOutcome::AlreadyFired]
+        );
+        assert_eq!(b.buggify_outcome(0.0), BuggifyOutcome::NotActivated);
+    }
+
+    #[test]
+    fn test_buggify_on_nth() {
+        let b = Buggifier::default();
+        assert!(!b.buggify_on_nth(1));
+
+        b.enable_buggify_with_seed(42);
+        let fired: Vec<bool> = (0..5).map(|_| b.buggify_on_nth(3)).collect();
+        assert_eq!(fired, vec![false, false, true, false, false]);
+        assert_eq!(b.activation_count(), 1);
+
+        // counting restarts once buggify is enabled again
+        b.disable_buggify();
+        b.enable_buggify_with_seed(7);
+        let fired: Vec<bool> = (0..3).map(|_| b.buggify_on_nth(2)).collect();
+        assert_eq!(fired, vec![false, true, false]);
+
+        // the fault budget applies
+        b.disable_buggify();
+        b.set_fault_budget(Some(0));
+        b.enable_buggify_with_seed(7);
+        let fired: Vec<bool> = (0..3).map(|_| b.buggify_on_nth(2)).collect();
+        assert_eq!(fired, vec![false, false, false]);
+        b.set_fault_budget(None);
+
+        // the decisions are recorded, and replayed
+        b.disable_buggify();
+        b.enable_buggify_with_seed(7);
+        b.record_decisions();
+        let fired: Vec<bool> = (0..3).map(|_| b.buggify_on_nth(2)).collect();
+        assert_eq!(fired, vec![false, true, false]);
+        let decisions = b.recorded_decisions().expect("should be recording");
+        assert_eq!(decisions, vec![false, true]);
+
+        b.disable_buggify();
+        b.enable_buggify_with_seed(7);
+        b.replay_decisions(vec![true]);
+        assert!(b.buggify_on_nth(2));
+    }
+
+    #[test]
+    fn test_chaos_mode() {
+        // how many runs had the site fire
+        let activations = |mode| {
+            let b = Buggifier::default();
+            b.set_mode(mode);
+            (0..1000)
+                .map(|seed| {
+                    b.enable_buggify_with_seed(seed);
+                    let fired = b.buggify() as u64 + b.buggify_with_ratio(1, 100) as u64;
+                    b.disable_buggify();
+                    fired
+                })
+                .sum::<u64>()
+        };
+        let normal = activations(BuggifyMode::Normal);
+        let chaos = activations(BuggifyMode::chaos());
+        assert!(chaos > normal, "chaos: {}, normal: {}", chaos, normal);
+        // reproducible under the same seeds
+        assert_eq!(activations(BuggifyMode::chaos()), chaos);
+        // a floor of 0 is the normal mode
+        assert_eq!(activations(BuggifyMode::Chaos { floor: 0.0 }), normal);
+    }
+
+    #[test]
+    fn test_buggify_mode_from_str() {
+        assert_eq!("normal".parse(), Ok(BuggifyMode::Normal));
+        assert_eq!("chaos".parse(), Ok(BuggifyMode::chaos()));
+        assert_eq!("chaos=0.5".parse(), Ok(BuggifyMode::Chaos { floor: 0.5 }));
+        assert!("chaos=high".parse::<BuggifyMode>().is_err());
+        assert!("wild".parse::<BuggifyMode>().is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "buggify"))]
+    fn test_static_buggify() {
+        use crate::{
+            buggify_at, buggify_in_group, buggify_on_nth, buggify_outcome, buggify_with_ratio,
+        };
+
+        enable_buggify(SmallRng::seed_from_u64(42));
+        assert!(is_buggify_enabled());
+        assert!(!buggify());
+        assert!(!buggify_with_prob(1.0));
+        assert!(!buggify_with_ratio(1, 1));
+        assert!(!buggify_on_nth(1));
+        assert!(!buggify_in_group("disk", 1.0));
+        assert!(!buggify_at(Location::caller(), 1.0));
+        assert_eq!(buggify_outcome(1.0), BuggifyOutcome::Disabled);
+        // the sites are compiled out, the buggifier never saw them
+        assert_eq!(buggifier().evaluation_count(), 0);
+        assert!(buggifier().inner.lock().buggified_lines.is_empty());
+        disable_buggify();
+    }
 }
@@ -1,10 +1,6 @@
 extern crate circus_simulation;
 
-use circus_simulation::deterministic::platform::SimulationPlatform;
-use circus_simulation::deterministic::runtime::executor::DeterministicExecutor;
-use circus_simulation::deterministic::runtime::reactor::DeterministicReactor;
-use circus_simulation::deterministic::runtime::task::Task;
-use circus_simulation::platform::{Platform, PlatformProvider};
+use circus_simulation::prelude::*;
 use std::time::Duration;
 use tracing::Level;
 
@@ -13,18 +9,11 @@ fn main() {
         .with_max_level(Level::TRACE)
         .init();
 
-    // let's create an Deterministic executor and runtime
-    let reactor = DeterministicReactor::default();
-    let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
-
-    // let's create a simulated platform. You can swap implementation between:
+    // `run` creates a deterministic executor and reactor, then a simulated platform seeded
+    // with 42, and runs our async function to completion. You can swap the platform between:
     // * production, allowing you to talk to your OS,
     // * dev, with an buggified deterministic simulation.
-    let platform: PlatformProvider = SimulationPlatform::new(42, reactor).into();
-
-    // let's run our async function
-    executor.spawn(Task::new(run_platform(platform)));
-    executor.run();
+    circus_simulation::run(42, |platform| run_platform(platform.into()));
 }
 
 async fn run_platform(mut platform: PlatformProvider) {
@@ -34,7 +23,7 @@ async fn run_platform(mut platform: PlatformProvider) {
     let start_time = platform.now();
 
     // We are going to loop opening a file.
-    for i in 0..10 {
+    for i in 0..12 {
         let file_result = platform.open("/etc/hosts".as_ref()).await;
         // here, with the seed 42, the first `open` will take 817ms.
         // Don't worry, this is simulated time, so we are not waiting 817ms!
@@ -44,8 +33,8 @@ async fn run_platform(mut platform: PlatformProvider) {
                 .duration_since(start_time)
                 .eq(&Duration::from_millis(817)));
         }
-        if i == 8 {
-            // using the seed 42, the 8th opening will trigger an error.
+        if i == 11 {
+            // using the seed 42, the 12th opening will trigger an error.
             assert!(file_result.is_err());
         } else {
             assert!(file_result.is_ok());
@@ -0,0 +1,71 @@
+//! Assertions module
+use crate::deterministic::platform::SimulationPlatform;
+use crate::run::run;
+use std::fmt::Debug;
+use std::future::Future;
+
+/// Runs `simulation` twice with the same `seed`, each time with [`run`], and returns its output.
+///
+/// # Panics
+/// Panics if both outputs differ, meaning that something in the simulation is not deterministic,
+/// such as the iteration order of a `HashMap` or the use of real time.
+pub fn assert_deterministic<F, Fut, T>(seed: u64, simulation: F) -> T
+where
+    F: Fn(SimulationPlatform) -> Fut,
+    Fut: Future<Output = T> + 'static,
+    T: PartialEq + Debug + 'static,
+{
+    let first = run(seed, &simulation);
+    let second = run(seed, &simulation);
+    assert_eq!(
+        first, second,
+        "simulation with seed {} is not deterministic",
+        seed
+    );
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertions::assert_deterministic;
+    use crate::deterministic::random::sim_rng;
+    use crate::platform::Platform;
+    use rand::Rng;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn test_deterministic() {
+        let elapsed = assert_deterministic(42, |mut platform| async move {
+            let start = platform.now();
+            let mut results = vec![];
+            for _ in 0..12 {
+                results.push(platform.open(Path::new("/etc/hosts")).await.is_ok());
+            }
+            (results, platform.now().duration_since(start))
+        });
+        assert_eq!(elapsed.0.iter().filter(|ok| !**ok).count(), 1);
+    }
+
+    #[test]
+    fn test_sim_rng() {
+        let draw = || {
+            assert_deterministic(42, |_platform| async {
+                let mut rng = sim_rng();
+                (0..10)
+                    .map(|_| rng.gen_range(0..1000))
+                    .collect::<Vec<u64>>()
+            })
+        };
+        assert_eq!(draw(), draw());
+    }
+
+    #[test]
+    #[should_panic(expected = "is not deterministic")]
+    fn test_not_deterministic() {
+        static RUNS: AtomicU64 = AtomicU64::new(0);
+        assert_deterministic(42, |_platform| async {
+            RUNS.fetch_add(1, Ordering::Relaxed)
+        });
+    }
+}
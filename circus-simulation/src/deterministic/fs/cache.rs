@@ -0,0 +1,65 @@
+//! Simulated page cache module
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Models the page cache of the simulated file system: a path accessed again within `window`
+/// of its last access is served from the cache, and is much faster to open.
+/// Clones are sharing the same cache.
+#[derive(Debug, Clone)]
+pub struct PageCache {
+    window: Duration,
+    entries: Arc<Mutex<BTreeMap<PathBuf, Instant>>>,
+}
+
+impl PageCache {
+    /// creates an empty `PageCache`, keeping a path cached for `window` after its last access
+    pub fn new(window: Duration) -> Self {
+        PageCache {
+            window,
+            entries: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// records an access to `path` at `now`, and returns whether it was served from the cache
+    pub fn access(&self, path: &Path, now: Instant) -> bool {
+        let mut entries = self.entries.lock();
+        let cached = entries.get(path).map_or(false, |last| {
+            now.saturating_duration_since(*last) <= self.window
+        });
+        entries.insert(path.to_path_buf(), now);
+        cached
+    }
+
+    /// evicts every path from the cache
+    pub fn evict(&self) {
+        self.entries.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::fs::cache::PageCache;
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_page_cache() {
+        let cache = PageCache::new(Duration::from_secs(10));
+        let path = Path::new("/etc/hosts");
+        let start = Instant::now();
+
+        assert!(!cache.access(path, start), "first access should be cold");
+        assert!(cache.access(path, start + Duration::from_secs(5)));
+        assert!(cache.access(path, start + Duration::from_secs(15)));
+        assert!(!cache.access(Path::new("/etc/passwd"), start));
+
+        // outside of the window
+        assert!(!cache.access(path, start + Duration::from_secs(30)));
+
+        cache.clone().evict();
+        assert!(!cache.access(path, start + Duration::from_secs(31)));
+    }
+}
@@ -0,0 +1,214 @@
+//! Simulated disk module
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// State of the disk of a simulated node, shared by its platform and its files,
+/// used to simulate power losses. Clones are sharing the same disk.
+/// The state is only kept in memory: the unsynced bytes are buffered by the
+/// [`crate::deterministic::fs::file::SimulatedFile`], and a crash only changes what the
+/// simulation sees, never the files of the host.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedDisk {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    // the seed of the platform, the random sources of the files are derived from it
+    seed: u64,
+    crashed: bool,
+    // number of crashes so far
+    generation: u64,
+    // identity of the file at each path, see `SimulatedDisk::file_id`
+    file_ids: BTreeMap<PathBuf, u64>,
+    next_file_id: u64,
+    // files created since their directory was last synced, see `SimulatedDisk::created`
+    undurable: BTreeSet<PathBuf>,
+    // files lost in a crash, they are still on the host but hidden from the simulation
+    lost: BTreeSet<PathBuf>,
+    // directories seen by the simulation, see `SimulatedDisk::add_directory`
+    directories: BTreeSet<PathBuf>,
+}
+
+/// returns the error of opening a directory as a file, the same on every OS
+pub fn is_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "is a directory")
+}
+
+impl SimulatedDisk {
+    /// creates a new `SimulatedDisk`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// creates a new `SimulatedDisk`, deriving the random sources of its files from `seed`
+    pub fn with_seed(seed: u64) -> Self {
+        let disk = Self::default();
+        disk.inner.lock().seed = seed;
+        disk
+    }
+
+    /// returns the seed given to [`SimulatedDisk::with_seed`], 0 by default
+    pub fn seed(&self) -> u64 {
+        self.inner.lock().seed
+    }
+
+    /// returns the number of crashes so far
+    pub fn generation(&self) -> u64 {
+        self.inner.lock().generation
+    }
+
+    /// returns true if the node crashed and has not been restarted yet
+    pub fn is_crashed(&self) -> bool {
+        self.inner.lock().crashed
+    }
+
+    /// crashes the node: every operation fails until [`SimulatedDisk::restart`].
+    /// The files created since their directory was last synced are lost: they are not found
+    /// by the simulation until they are created again. The files of the host are never touched.
+    pub fn crash(&self) {
+        let mut inner = self.inner.lock();
+        inner.crashed = true;
+        inner.generation += 1;
+        for path in std::mem::take(&mut inner.undurable) {
+            tracing::info!("lost file {:?}, its directory was not synced", path);
+            inner.lost.insert(path);
+        }
+    }
+
+    /// restarts a crashed node
+    pub fn restart(&self) {
+        self.inner.lock().crashed = false;
+    }
+
+    /// records that the file at `path` was created: it is lost on crash until its directory
+    /// is synced, see [`SimulatedDisk::sync_dir`]
+    pub fn created(&self, path: &Path) {
+        let mut inner = self.inner.lock();
+        inner.lost.remove(path);
+        inner.undurable.insert(path.to_path_buf());
+    }
+
+    /// returns true if the file at `path` was lost in a crash, see [`SimulatedDisk::crash`]
+    pub fn is_lost(&self, path: &Path) -> bool {
+        self.inner.lock().lost.contains(path)
+    }
+
+    /// returns a [`io::ErrorKind::NotFound`] error if the file at `path` was lost in a crash
+    pub fn check_path(&self, path: &Path) -> io::Result<()> {
+        if self.is_lost(path) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the file was lost in a crash",
+            ));
+        }
+        Ok(())
+    }
+
+    /// makes the files created in `directory` so far survive crashes
+    pub fn sync_dir(&self, directory: &Path) {
+        self.inner
+            .lock()
+            .undurable
+            .retain(|path| path.parent() != Some(directory));
+    }
+
+    /// records that `path` is a directory: opening it as a file fails with [`is_a_directory`]
+    pub fn add_directory(&self, path: &Path) {
+        self.inner.lock().directories.insert(path.to_path_buf());
+    }
+
+    /// returns true if `path` was recorded as a directory, see [`SimulatedDisk::add_directory`]
+    pub fn is_directory(&self, path: &Path) -> bool {
+        self.inner.lock().directories.contains(path)
+    }
+
+    /// returns the identity of the file at `path`, assigned the first time the path is seen
+    pub fn file_id(&self, path: &Path) -> u64 {
+        let mut inner = self.inner.lock();
+        if let Some(id) = inner.file_ids.get(path) {
+            return *id;
+        }
+        Self::assign_file_id(&mut inner, path)
+    }
+
+    /// simulates another file taking the place of the file at `path`, like a rename over it,
+    /// returning the new identity of the path
+    pub fn replace(&self, path: &Path) -> u64 {
+        Self::assign_file_id(&mut self.inner.lock(), path)
+    }
+
+    fn assign_file_id(inner: &mut Inner, path: &Path) -> u64 {
+        let id = inner.next_file_id;
+        inner.next_file_id += 1;
+        inner.file_ids.insert(path.to_path_buf(), id);
+        id
+    }
+
+    /// returns an error if the node is crashed, or crashed since `generation`
+    pub fn check(&self, generation: u64) -> io::Result<()> {
+        let inner = self.inner.lock();
+        if inner.crashed || inner.generation != generation {
+            return Err(io::Error::new(io::ErrorKind::Other, "the node crashed"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::fs::disk::SimulatedDisk;
+    use std::io::ErrorKind;
+    use std::path::Path;
+
+    #[test]
+    fn test_disk() {
+        let disk = SimulatedDisk::new();
+        let generation = disk.generation();
+        assert!(disk.check(generation).is_ok());
+
+        disk.clone().crash();
+        assert!(disk.is_crashed());
+        assert!(disk.check(disk.generation()).is_err());
+
+        disk.restart();
+        assert!(!disk.is_crashed());
+        assert!(disk.check(disk.generation()).is_ok());
+        assert!(disk.check(generation).is_err(), "crash is not seen");
+    }
+
+    #[test]
+    fn test_file_ids() {
+        let disk = SimulatedDisk::new();
+        let (a, b) = (Path::new("/data/a"), Path::new("/data/b"));
+        let id = disk.file_id(a);
+        assert_eq!(disk.clone().file_id(a), id);
+        assert_ne!(disk.file_id(b), id);
+
+        let replaced = disk.replace(a);
+        assert_ne!(replaced, id);
+        assert_eq!(disk.file_id(a), replaced);
+    }
+
+    #[test]
+    fn test_lost_files() {
+        let disk = SimulatedDisk::new();
+        let (synced, lost) = (Path::new("/data/synced"), Path::new("/data/lost"));
+        disk.created(synced);
+        disk.sync_dir(Path::new("/data"));
+        disk.created(lost);
+
+        disk.crash();
+        disk.restart();
+        assert!(disk.check_path(synced).is_ok());
+        let error = disk.check_path(lost).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+
+        // creating the file again brings it back
+        disk.created(lost);
+        assert!(!disk.is_lost(lost));
+    }
+}
@@ -1,14 +1,239 @@
 //! Simulated file module
+use crate::deterministic::fs::disk::SimulatedDisk;
+use crate::deterministic::platform::FAULT_BIT_ROT;
+use crate::deterministic::random::DeterministicRandom;
+use crate::file::FileTrait;
+use async_trait::async_trait;
+use circus_buggify::Buggifier;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
 
-#[allow(dead_code)]
-/// Simulation implementation of a file.
+/// Silent corruption of the data read from a file. The probability to flip a bit during a read
+/// is `probability` per `per_bytes` bytes read, capped at `probability`, so a read flips
+/// at most one bit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BitRot {
+    /// probability to flip a bit every `per_bytes` bytes
+    pub probability: f64,
+    /// amount of bytes read associated with `probability`
+    pub per_bytes: u64,
+}
+
+impl BitRot {
+    /// returns the probability to flip a bit during a read of `read` bytes
+    pub(crate) fn read_probability(&self, read: usize) -> f64 {
+        let probability = self.probability.clamp(0.0, 1.0);
+        (probability * read as f64 / self.per_bytes.max(1) as f64).min(probability)
+    }
+}
+
+/// Simulation implementation of a file. When the file is on a [`SimulatedDisk`],
+/// written bytes are buffered until the file is synced or dropped, and are lost if
+/// the node crashes before. Reads are seeing the buffered bytes.
+/// The position of the file is tracked by the simulation, see [`FileTrait::seek`].
 pub struct SimulatedFile {
     file: std::fs::File,
+    random: DeterministicRandom,
+    buggifier: Arc<Buggifier>,
+    bit_rot: Option<BitRot>,
+    // the bits to flip are drawn apart, so the bit rot of a file does not depend on the others
+    bit_rot_random: DeterministicRandom,
+    // the disk, and its generation when the file was opened
+    disk: Option<(SimulatedDisk, u64)>,
+    // buffered writes, with their position, in the order they were written
+    unsynced: Vec<(u64, Vec<u8>)>,
+    position: u64,
+    short_writes: f64,
+    file_id: u64,
 }
 
 impl SimulatedFile {
     /// creates a `SimulatedFile`
-    pub fn new(file: std::fs::File) -> Self {
-        SimulatedFile { file }
+    pub fn new(
+        file: std::fs::File,
+        random: DeterministicRandom,
+        buggifier: Arc<Buggifier>,
+        bit_rot: Option<BitRot>,
+    ) -> Self {
+        SimulatedFile {
+            file,
+            random,
+            buggifier,
+            bit_rot,
+            bit_rot_random: DeterministicRandom::new_with_seed(0),
+            disk: None,
+            unsynced: vec![],
+            position: 0,
+            short_writes: 0.0,
+            file_id: 0,
+        }
+    }
+
+    /// puts the file on the `disk`, so it is affected by its crashes
+    pub fn on_disk(mut self, disk: SimulatedDisk) -> Self {
+        let generation = disk.generation();
+        self.disk = Some((disk, generation));
+        self
+    }
+
+    /// makes each write only write a random part of its buffer, with the given probability
+    pub fn with_short_writes(mut self, probability: f64) -> Self {
+        self.short_writes = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// seeds the random source drawing the bits flipped by the [`BitRot`]
+    pub fn with_bit_rot_seed(mut self, seed: u64) -> Self {
+        self.bit_rot_random = DeterministicRandom::new_with_seed(seed);
+        self
+    }
+
+    /// sets the identity of the file, see [`FileTrait::file_id`]
+    pub fn with_file_id(mut self, file_id: u64) -> Self {
+        self.file_id = file_id;
+        self
+    }
+
+    /// returns an error if the node crashed since the file was opened
+    fn check_disk(&self) -> io::Result<()> {
+        match &self.disk {
+            Some((disk, generation)) => disk.check(*generation),
+            None => Ok(()),
+        }
+    }
+
+    /// returns the length of the file, including the buffered writes
+    fn len(&self) -> io::Result<u64> {
+        let written = self
+            .unsynced
+            .iter()
+            .map(|(position, bytes)| position + bytes.len() as u64);
+        Ok(written.fold(self.file.metadata()?.len(), u64::max))
+    }
+
+    /// writes the buffered bytes at their position
+    fn flush_unsynced(&mut self) -> io::Result<()> {
+        for (position, bytes) in self.unsynced.drain(..) {
+            self.file.seek(SeekFrom::Start(position))?;
+            self.file.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SimulatedFile {
+    fn drop(&mut self) {
+        // closing the file is handing the buffered bytes to the OS
+        if !self.unsynced.is_empty() && self.check_disk().is_ok() {
+            if let Err(error) = self.flush_unsynced() {
+                tracing::warn!("could not write buffered bytes on close: {}", error);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl FileTrait for SimulatedFile {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check_disk()?;
+        let available = self.len()?.saturating_sub(self.position);
+        let read = buf.len().min(available as usize);
+        let buf = &mut buf[..read];
+        // bytes past the end of the OS file are only buffered, holes are zeroes
+        buf.fill(0);
+        self.file.seek(SeekFrom::Start(self.position))?;
+        let mut filled = 0;
+        while filled < read {
+            match self.file.read(&mut buf[filled..])? {
+                0 => break,
+                bytes => filled += bytes,
+            }
+        }
+        let end = self.position + read as u64;
+        for (position, bytes) in self.unsynced.iter() {
+            let start = (*position).max(self.position);
+            let stop = (position + bytes.len() as u64).min(end);
+            if start < stop {
+                buf[(start - self.position) as usize..(stop - self.position) as usize]
+                    .copy_from_slice(
+                        &bytes[(start - position) as usize..(stop - position) as usize],
+                    );
+            }
+        }
+        self.position = end;
+
+        if let Some(bit_rot) = self.bit_rot {
+            if read > 0
+                && self
+                    .buggifier
+                    .buggify_in_group(FAULT_BIT_ROT, bit_rot.read_probability(read))
+            {
+                let position = self.bit_rot_random.random_between(0..read);
+                let bit = self.bit_rot_random.random_between(0..8);
+                buf[position] ^= 1 << bit;
+                tracing::info!("buggified read: flipped bit {} of byte {}", bit, position);
+            }
+        }
+        Ok(read)
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_disk()?;
+        let short = self.short_writes > 0.0 && buf.len() > 1;
+        let buf = if short && self.random.random_boolean(self.short_writes) {
+            let written = self.random.random_between(1..buf.len());
+            tracing::info!("short write of {} bytes out of {}", written, buf.len());
+            &buf[..written]
+        } else {
+            buf
+        };
+        let written = if self.disk.is_none() {
+            self.file.seek(SeekFrom::Start(self.position))?;
+            self.file.write(buf)?
+        } else {
+            self.unsynced.push((self.position, buf.to_vec()));
+            buf.len()
+        };
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    async fn sync_all(&mut self) -> io::Result<()> {
+        self.check_disk()?;
+        self.flush_unsynced()?;
+        self.file.sync_all()
+    }
+
+    async fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.check_disk()?;
+        let (base, offset) = match position {
+            SeekFrom::Start(offset) => {
+                self.position = offset;
+                return Ok(offset);
+            }
+            SeekFrom::Current(offset) => (self.position, offset),
+            SeekFrom::End(offset) => (self.len()?, offset),
+        };
+        let position = if offset < 0 {
+            base.checked_sub(offset.unsigned_abs())
+        } else {
+            base.checked_add(offset as u64)
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    fn file_id(&self) -> io::Result<u64> {
+        self.check_disk()?;
+        Ok(self.file_id)
     }
 }
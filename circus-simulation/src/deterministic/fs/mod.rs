@@ -1,2 +1,4 @@
 //! Deterministic file system module
+pub mod cache;
+pub mod disk;
 pub mod file;
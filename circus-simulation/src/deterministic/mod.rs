@@ -1,6 +1,11 @@
 //! Deterministic scheduling, IO and fault injection
+#[cfg(feature = "simulation")]
 pub mod fs;
+#[cfg(feature = "simulation")]
+pub mod net;
+#[cfg(feature = "simulation")]
 pub mod platform;
 pub mod random;
 pub mod runtime;
 pub mod time;
+pub mod trace;
@@ -0,0 +1,249 @@
+//! Simulated network addresses and links module
+use crate::deterministic::random::DeterministicRandom;
+use crate::deterministic::runtime::reactor::DeterministicReactor;
+use crate::deterministic::runtime::timer::DeterministicTimer;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+/// Address of a simulated node, displayed like a socket address, such as `10.12.0.7:4500`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SimAddr {
+    ip: Ipv4Addr,
+    port: u16,
+}
+
+impl SimAddr {
+    /// creates a new `SimAddr`
+    pub fn new(ip: Ipv4Addr, port: u16) -> Self {
+        SimAddr { ip, port }
+    }
+
+    /// returns the IP of the node
+    pub fn ip(&self) -> Ipv4Addr {
+        self.ip
+    }
+
+    /// returns the port of the node
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl fmt::Display for SimAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.ip, self.port)
+    }
+}
+
+impl From<SimAddr> for SocketAddr {
+    fn from(addr: SimAddr) -> Self {
+        SocketAddr::V4(SocketAddrV4::new(addr.ip, addr.port))
+    }
+}
+
+/// Allocates unique addresses to the simulated nodes, in the private `10.0.0.0/8` network.
+/// The addresses are drawn from a [`DeterministicRandom`], so a seed always gives the same
+/// addresses in the same order.
+#[derive(Debug, Clone)]
+pub struct AddrAllocator {
+    random: DeterministicRandom,
+    allocated: BTreeSet<SimAddr>,
+}
+
+impl AddrAllocator {
+    /// creates a new `AddrAllocator`, drawing the addresses from `random`
+    pub fn new(random: DeterministicRandom) -> Self {
+        AddrAllocator {
+            random,
+            allocated: BTreeSet::new(),
+        }
+    }
+
+    /// returns a new address, never returned before by this allocator
+    pub fn allocate(&mut self) -> SimAddr {
+        loop {
+            let ip = Ipv4Addr::new(
+                10,
+                self.random.random_inclusive(0..=255),
+                self.random.random_inclusive(0..=255),
+                // avoiding the network and broadcast addresses
+                self.random.random_between(1..255),
+            );
+            let addr = SimAddr::new(ip, self.random.random_inclusive(1024..=u16::MAX));
+            if self.allocated.insert(addr) {
+                tracing::trace!("allocated address {}", addr);
+                return addr;
+            }
+        }
+    }
+
+    /// returns the addresses allocated so far, sorted
+    pub fn allocated(&self) -> Vec<SimAddr> {
+        self.allocated.iter().copied().collect()
+    }
+}
+
+/// Latency of the messages sent on a link, which can get congested. A congestion episode starts
+/// with `probability` on each message, drawn from a [`DeterministicRandom`]. During an episode,
+/// the latency ramps up linearly to `latency + peak` over `ramp` messages, then recovers to
+/// `latency` over as many messages. There is no simulated stream yet, this is the model a
+/// network layer will apply to each send.
+#[derive(Debug, Clone)]
+pub struct Congestion {
+    random: DeterministicRandom,
+    latency: Duration,
+    probability: f64,
+    peak: Duration,
+    ramp: u64,
+    // messages sent since the episode started, `None` when the link is not congested
+    step: Option<u64>,
+}
+
+impl Congestion {
+    /// creates a link delaying every message by `latency`, never congested until
+    /// [`Congestion::with_episodes`]
+    pub fn new(random: DeterministicRandom, latency: Duration) -> Self {
+        Congestion {
+            random,
+            latency,
+            probability: 0.0,
+            peak: Duration::ZERO,
+            ramp: 0,
+            step: None,
+        }
+    }
+
+    /// starts a congestion episode with `probability` on each message, adding up to `peak`
+    /// to the latency after `ramp` messages
+    pub fn with_episodes(mut self, probability: f64, peak: Duration, ramp: u64) -> Self {
+        self.probability = probability.clamp(0.0, 1.0);
+        self.peak = peak;
+        self.ramp = ramp.max(1);
+        self
+    }
+
+    /// returns true during a congestion episode
+    pub fn is_congested(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// returns the latency of the next message sent on the link
+    pub fn next_latency(&mut self) -> Duration {
+        let step = match self.step {
+            Some(step) => step + 1,
+            None if self.probability > 0.0 && self.random.random_boolean(self.probability) => {
+                tracing::info!("buggified link: congestion episode starting");
+                1
+            }
+            None => return self.latency,
+        };
+        // ramping up, then recovering
+        let distance = step.min(2 * self.ramp - step);
+        self.step = if step < 2 * self.ramp {
+            Some(step)
+        } else {
+            None
+        };
+        let extra = self.peak.mul_f64(distance as f64 / self.ramp as f64);
+        self.latency.saturating_add(extra)
+    }
+
+    /// waits for the latency of the next message on the `reactor`, and returns it
+    pub async fn delay(&mut self, reactor: &DeterministicReactor) -> Duration {
+        let latency = self.next_latency();
+        let time = reactor.get_deterministic_time();
+        DeterministicTimer::wait_with_reactor(time, reactor.clone(), latency).await;
+        latency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::net::{AddrAllocator, Congestion, SimAddr};
+    use crate::deterministic::random::DeterministicRandom;
+    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::reactor::DeterministicReactor;
+    use crate::deterministic::runtime::task::Task;
+    use parking_lot::RwLock;
+    use std::collections::BTreeSet;
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn allocate(seed: u64, count: usize) -> Vec<SimAddr> {
+        let mut allocator = AddrAllocator::new(DeterministicRandom::new_with_seed(seed));
+        (0..count).map(|_| allocator.allocate()).collect()
+    }
+
+    #[test]
+    fn test_allocate() {
+        let addrs = allocate(42, 100);
+        assert_eq!(addrs.iter().collect::<BTreeSet<_>>().len(), 100);
+        assert_eq!(allocate(42, 100), addrs);
+        assert_ne!(allocate(43, 100), addrs);
+        for addr in addrs {
+            assert_eq!(addr.ip().octets()[0], 10);
+            assert!(addr.port() >= 1024);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let addr = SimAddr::new(Ipv4Addr::new(10, 12, 0, 7), 4500);
+        assert_eq!(addr.to_string(), "10.12.0.7:4500");
+        assert_eq!(addr.to_string().parse::<SocketAddr>(), Ok(addr.into()));
+    }
+
+    /// sends a burst of `messages` on a link with seed `seed`, returning the simulated time
+    /// each message took
+    fn send_burst(seed: u64, messages: usize) -> Vec<Duration> {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let latencies = Arc::new(RwLock::new(Vec::new()));
+
+        let result = latencies.clone();
+        executor.spawn(Task::new(async move {
+            let random = DeterministicRandom::new_with_seed(seed);
+            let mut link = Congestion::new(random, Duration::from_millis(1)).with_episodes(
+                0.2,
+                Duration::from_millis(40),
+                4,
+            );
+            let time = reactor.get_deterministic_time();
+            for _ in 0..messages {
+                let start = time.now();
+                let latency = link.delay(&reactor).await;
+                assert_eq!(time.now().duration_since(start), latency);
+                result.write().push(latency);
+            }
+        }));
+        executor.run();
+
+        let latencies = latencies.read().clone();
+        latencies
+    }
+
+    #[test]
+    fn test_congestion() {
+        let latencies = send_burst(42, 64);
+        assert_eq!(send_burst(42, 64), latencies);
+
+        // every episode ramps up to the peak, then recovers
+        let millis: Vec<u128> = latencies.iter().map(Duration::as_millis).collect();
+        let episode = [11, 21, 31, 41, 31, 21, 11, 1];
+        let start = millis
+            .iter()
+            .position(|latency| *latency > 1)
+            .expect("the link should get congested");
+        assert_eq!(millis[start..start + episode.len()], episode);
+        assert!(millis.iter().all(|latency| *latency <= 41));
+
+        // without episodes, the latency is constant
+        let random = DeterministicRandom::new_with_seed(42);
+        let mut link = Congestion::new(random, Duration::from_millis(1));
+        assert!((0..64).all(|_| link.next_latency() == Duration::from_millis(1)));
+        assert!(!link.is_congested());
+    }
+}
@@ -1,22 +1,58 @@
 //! Deterministic platform module
-use crate::deterministic::fs::file::SimulatedFile;
+use crate::deterministic::fs::cache::PageCache;
+use crate::deterministic::fs::disk::{is_a_directory, SimulatedDisk};
+use crate::deterministic::fs::file::{BitRot, SimulatedFile};
 use crate::deterministic::random::DeterministicRandom;
+use crate::deterministic::runtime::interval::IntervalStream;
 use crate::deterministic::runtime::reactor::DeterministicReactor;
+use crate::deterministic::runtime::timeout::{timeout, Timeout};
 use crate::deterministic::runtime::timer::DeterministicTimer;
-use crate::deterministic::time::DeterministicTime;
+use crate::deterministic::time::{ClockSkew, DeterministicTime};
+use crate::deterministic::trace::{ReplayDivergence, Trace};
 use crate::file::File;
 use crate::platform::Platform;
+use crate::production::platform::create_file;
 use async_trait::async_trait;
+use std::future::Future;
 use std::io;
 use std::io::Error;
 use std::io::ErrorKind;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use circus_buggify::Buggifier;
+use circus_buggify::{Buggifier, BuggifyMode};
 use rand::rngs::SmallRng;
 use rand::SeedableRng;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+
+/// label of the I/O errors injected by the platform, see [`SimulationPlatform::assert_injected`]
+pub const FAULT_IO_ERROR: &str = "io_error";
+/// label of the clock skews injected by [`SimulationPlatform::buggify_clock_skew`]
+pub const FAULT_CLOCK_SKEW: &str = "clock_skew";
+/// label of the clock drifts injected by [`SimulationPlatform::buggify_clock_drift`]
+pub const FAULT_CLOCK_DRIFT: &str = "clock_drift";
+/// label of the evictions of the page cache, see [`SimulationPlatform::with_page_cache`]
+pub const FAULT_PAGE_CACHE_EVICTION: &str = "page_cache_eviction";
+/// label of the bits flipped by the bit rot, see [`SimulationPlatform::with_bit_rot`]
+pub const FAULT_BIT_ROT: &str = "bit_rot";
+/// label of the files replaced behind an open, see [`SimulationPlatform::with_file_replacements`]
+pub const FAULT_FILE_REPLACED: &str = "file_replaced";
+
+/// Derives the seed of the buggifier from the seed of the platform, by applying one round of
+/// SplitMix64. The buggify decisions and the random values of the platform are then drawn from
+/// two unrelated streams, while both are still given by the single seed of the simulation.
+fn buggify_seed(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Derives the seed of the bit rot of the file `file_id` from the seed of the platform, so the
+/// bits flipped in a file only depend on the reads of that file.
+fn bit_rot_seed(seed: u64, file_id: u64) -> u64 {
+    buggify_seed(buggify_seed(seed) ^ file_id)
+}
 
 /// Simulated version of the plateform. Every API exposed is subject to an deterministic output,
 /// including:
@@ -29,21 +65,313 @@ pub struct SimulationPlatform {
     random: DeterministicRandom,
     reactor: DeterministicReactor,
     buggifier: Arc<Buggifier>,
+    start: Instant,
+    bit_rot: Option<BitRot>,
+    page_cache: Option<PageCache>,
+    disk: SimulatedDisk,
+    short_writes: f64,
+    file_replacements: bool,
 }
 
 impl SimulationPlatform {
     /// This will:
     /// * enable buggify
     /// * start the simulation reactor
+    ///
+    /// The random values, such as latencies, are drawn from `seed`, while the buggify decisions
+    /// are drawn from a seed derived from it, so both streams do not alias.
     pub fn new(seed: u64, reactor: DeterministicReactor) -> Self {
         let random = DeterministicRandom::new_with_seed(seed);
+        Self::with_random(
+            seed,
+            random,
+            Buggifier::new(SmallRng::seed_from_u64(buggify_seed(seed))),
+            reactor,
+        )
+    }
+
+    /// Like [`SimulationPlatform::new`], but recording every random value drawn and
+    /// buggify decision taken, see [`SimulationPlatform::trace`].
+    pub fn new_recording(seed: u64, reactor: DeterministicReactor) -> Self {
+        let random = DeterministicRandom::new_recording(seed);
+        let buggifier = Buggifier::new(SmallRng::seed_from_u64(buggify_seed(seed)));
+        buggifier.record_decisions();
+        Self::with_random(seed, random, buggifier, reactor)
+    }
+
+    /// Creates a platform replaying a [`Trace`] recorded by a platform created with
+    /// [`SimulationPlatform::new_recording`], instead of drawing random values and decisions.
+    /// The bits flipped by the bit rot are not part of the trace, they are drawn as for seed 0.
+    pub fn replay(trace: &Trace, reactor: DeterministicReactor) -> Self {
+        let random = DeterministicRandom::new_replaying(trace.draws.clone());
+        let buggifier = Buggifier::new(SmallRng::seed_from_u64(0));
+        buggifier.replay_decisions(trace.decisions.clone());
+        Self::with_random(0, random, buggifier, reactor)
+    }
+
+    fn with_random(
+        seed: u64,
+        random: DeterministicRandom,
+        buggifier: Buggifier,
+        reactor: DeterministicReactor,
+    ) -> Self {
+        let time = reactor.get_deterministic_time();
 
         SimulationPlatform {
-            time: reactor.get_deterministic_time(),
+            start: time.now(),
+            time,
             random,
             reactor,
-            buggifier: Arc::new(Buggifier::new(SmallRng::seed_from_u64(seed))),
+            buggifier: Arc::new(buggifier),
+            bit_rot: None,
+            page_cache: None,
+            disk: SimulatedDisk::with_seed(seed),
+            short_writes: 0.0,
+            file_replacements: false,
+        }
+    }
+
+    /// returns what has been recorded so far by a platform created with
+    /// [`SimulationPlatform::new_recording`], or `None` if the platform is not recording
+    pub fn trace(&self) -> Option<Trace> {
+        Some(Trace {
+            draws: self.random.recorded()?,
+            decisions: self.buggifier.recorded_decisions()?,
+        })
+    }
+
+    /// checks that a platform created with [`SimulationPlatform::replay`] followed its trace,
+    /// drawing as many random values and taking as many buggify decisions as recorded.
+    /// Returns the first mismatch, the draws being checked before the decisions.
+    pub fn check_replay(&self) -> Result<(), ReplayDivergence> {
+        match self.random.replay_progress() {
+            Some((recorded, requested)) if recorded != requested => {
+                return Err(ReplayDivergence::Draws {
+                    step: recorded.min(requested),
+                    recorded,
+                    requested,
+                })
+            }
+            _ => {}
+        }
+        match self.buggifier.replay_progress() {
+            Some((recorded, requested)) if recorded != requested => {
+                Err(ReplayDivergence::Decisions {
+                    step: recorded.min(requested),
+                    recorded,
+                    requested,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// returns the deterministic source of randomness of the platform
+    pub fn random(&self) -> DeterministicRandom {
+        self.random.clone()
+    }
+
+    /// returns the reactor the platform is waiting on
+    pub(crate) fn reactor(&self) -> DeterministicReactor {
+        self.reactor.clone()
+    }
+
+    /// Panics unless a fault labelled `label` was injected during the simulation, so a test can
+    /// make sure its seed actually exercised the failure path. The label is either one of the
+    /// `FAULT_*` constants of this module, a group given to
+    /// [`circus_buggify::Buggifier::buggify_in_group`], or a buggify site formatted as `file:line`.
+    #[track_caller]
+    pub fn assert_injected(&self, label: &str) {
+        self.buggifier.assert_injected(label);
+    }
+
+    /// enables silent corruption of the data read from the files opened by the platform
+    pub fn with_bit_rot(mut self, bit_rot: BitRot) -> Self {
+        self.bit_rot = Some(bit_rot);
+        self
+    }
+
+    /// enables short writes on the files opened by the platform: each write only writes
+    /// a random part of its buffer, with the given probability
+    pub fn with_short_writes(mut self, probability: f64) -> Self {
+        self.short_writes = probability;
+        self
+    }
+
+    /// enables buggified replacements of the files opened by the platform, as if another file
+    /// was renamed over them: the identity returned by [`crate::file::FileTrait::file_id`]
+    /// changes, while the path stays the same. See also [`SimulationPlatform::replace_file`].
+    pub fn with_file_replacements(mut self) -> Self {
+        self.file_replacements = true;
+        self
+    }
+
+    /// simulates another file taking the place of the file at `path`, like a rename over it.
+    /// The files opened afterwards have a new identity, see [`crate::file::FileTrait::file_id`].
+    pub fn replace_file(&self, path: &Path) {
+        let id = self.disk.replace(path);
+        tracing::info!("replaced file {:?}, now {}", path, id);
+    }
+
+    /// injects at most `budget` faults during the simulation, buggify never firing afterwards
+    pub fn with_fault_budget(self, budget: u64) -> Self {
+        self.buggifier.set_fault_budget(Some(budget));
+        self
+    }
+
+    /// sets how aggressively the platform injects faults, see [`BuggifyMode`], and
+    /// [`crate::deterministic::runtime::executor::ExecutorBuilder::buggify_mode`] to also
+    /// shuffle the tasks in chaos mode
+    pub fn with_buggify_mode(self, mode: BuggifyMode) -> Self {
+        self.buggifier.set_mode(mode);
+        self
+    }
+
+    /// enables a page cache model: a path opened again within `window` of its last open
+    /// is served from the cache, with a lower latency. Buggify can evict the cache.
+    pub fn with_page_cache(mut self, window: Duration) -> Self {
+        self.page_cache = Some(PageCache::new(window));
+        self
+    }
+
+    /// Simulates a power loss of the node: the bytes written but not synced are lost, the files
+    /// created since their directory was last synced with [`Platform::fsync_dir`] are lost,
+    /// the files opened are unusable, the operations in flight fail, and every operation fails
+    /// until [`SimulationPlatform::restart`]. Clones of the platform are crashing too.
+    pub fn crash(&mut self) {
+        tracing::info!("crashing the node");
+        self.disk.crash();
+    }
+
+    /// restarts the node after a [`SimulationPlatform::crash`]
+    pub fn restart(&mut self) {
+        tracing::info!("restarting the node");
+        self.disk.restart();
+    }
+
+    /// returns the simulated time elapsed since `earlier`, which must come from [`Platform::now`]
+    pub fn elapsed_since(&self, earlier: Instant) -> Duration {
+        self.now().saturating_duration_since(earlier)
+    }
+
+    /// returns the simulated time elapsed since the creation of the platform.
+    /// The clock skew of the platform is not applied.
+    pub fn since_start(&self) -> Duration {
+        self.reactor
+            .get_deterministic_time()
+            .now()
+            .saturating_duration_since(self.start)
+    }
+
+    /// waits for `future` for at most `duration` of simulated time, dropping it when the
+    /// deadline elapses, see [`crate::deterministic::runtime::timeout::timeout`]
+    pub fn timeout<F: Future>(&self, duration: Duration, future: F) -> Timeout<F> {
+        timeout(self.time.clone(), self.reactor.clone(), duration, future)
+    }
+
+    /// returns a stream yielding every `period` of simulated time
+    pub fn interval(&self, period: Duration) -> IntervalStream {
+        IntervalStream::new(self.time.clone(), self.reactor.clone(), period)
+    }
+
+    /// When buggified, skews the clock of the platform by a random offset of at most `max`,
+    /// ahead or behind the simulated timeline. Returns the applied skew.
+    pub fn buggify_clock_skew(&mut self, max: Duration) -> ClockSkew {
+        if !max.is_zero() && self.buggifier.buggify_in_group(FAULT_CLOCK_SKEW, 0.05) {
+            let offset = self.random.random_duration(Duration::ZERO..max);
+            let skew = if self.random.random_boolean(0.5) {
+                ClockSkew::Ahead(offset)
+            } else {
+                ClockSkew::Behind(offset)
+            };
+            tracing::info!("buggified clock skew: {:?}", skew);
+            self.time = self.time.with_skew(skew);
+        }
+        self.time.skew()
+    }
+
+    /// When buggified, makes the clock of the platform drift from the simulated timeline,
+    /// gaining or losing a random rate of at most `max_ppm` microseconds per second.
+    /// Returns the applied drift, in parts per million.
+    pub fn buggify_clock_drift(&mut self, max_ppm: u32) -> i64 {
+        if max_ppm > 0 && self.buggifier.buggify_in_group(FAULT_CLOCK_DRIFT, 0.05) {
+            let max_ppm = i64::from(max_ppm);
+            let drift = self.random.random_inclusive(-max_ppm..=max_ppm);
+            tracing::info!("buggified clock drift: {}ppm", drift);
+            self.time = self.time.with_drift(drift);
+        }
+        self.time.drift()
+    }
+
+    /// returns an I/O error when buggified, or else the latency of the operation, lower when
+    /// the data is `cached`. The latency and the kind of error are always drawn, in this order,
+    /// before the fault is decided, so the values drawn next do not depend on its outcome.
+    #[track_caller]
+    fn buggify_io_error(&mut self, cached: bool) -> Result<Duration, Error> {
+        let range = if cached { 1u64..10u64 } else { 300u64..2000u64 };
+        let latency = Duration::from_millis(self.random.random_between(range));
+        let kind = self.random.random_01();
+        if self.buggifier.buggify_in_group(FAULT_IO_ERROR, 0.05) {
+            return Err(io_error(kind));
         }
+        Ok(latency)
+    }
+
+    /// records an access to `path` in the page cache, if any, and returns whether it was cached.
+    /// The whole cache is evicted when buggified.
+    fn page_cache_access(&mut self, path: &Path) -> bool {
+        match &self.page_cache {
+            None => false,
+            Some(cache) => {
+                if self
+                    .buggifier
+                    .buggify_in_group(FAULT_PAGE_CACHE_EVICTION, 0.05)
+                {
+                    tracing::info!("buggified page cache eviction");
+                    cache.evict();
+                }
+                cache.access(path, self.time.now())
+            }
+        }
+    }
+
+    /// wraps a file opened by the platform at `path`
+    fn simulated_file(&mut self, file: std::fs::File, path: &Path) -> File {
+        if self.file_replacements && self.buggifier.buggify_in_group(FAULT_FILE_REPLACED, 0.05) {
+            self.replace_file(path);
+        }
+        let file_id = self.disk.file_id(path);
+        SimulatedFile::new(
+            file,
+            self.random.clone(),
+            self.buggifier.clone(),
+            self.bit_rot,
+        )
+        .on_disk(self.disk.clone())
+        .with_short_writes(self.short_writes)
+        .with_file_id(file_id)
+        .with_bit_rot_seed(bit_rot_seed(self.disk.seed(), file_id))
+        .into()
+    }
+
+    /// waits for the I/O latency drawn by [`SimulationPlatform::buggify_io_error`]
+    async fn io_latency(&self, latency: Duration) {
+        DeterministicTimer::wait_with_reactor(self.time.clone(), self.reactor.clone(), latency)
+            .await;
+    }
+}
+
+/// returns the I/O error injected for `probability`, a draw between 0 and 1
+fn io_error(probability: f32) -> Error {
+    // we cannot use float range in match
+    // issue #41620 <https://github.com/rust-lang/rust/issues/41620>
+    if probability < 0.1 {
+        Error::from(ErrorKind::UnexpectedEof)
+    } else if probability < 0.2 {
+        Error::from(ErrorKind::PermissionDenied)
+    } else {
+        // The system cannot find the file specified. (os error 2)
+        Error::from_raw_os_error(2)
     }
 }
 
@@ -51,72 +379,173 @@ impl SimulationPlatform {
 impl Platform for SimulationPlatform {
     // https://forums.foundationdb.org/t/simulation-of-disk-i-o/2937
     async fn open(&mut self, path: &Path) -> io::Result<File> {
-        if self.buggifier.buggify() {
-            let probability = self.random.random_01();
-            // we cannot use float range in match
-            // issue #41620 <https://github.com/rust-lang/rust/issues/41620>
-            let error = if probability < 0.1 {
-                Error::from(ErrorKind::UnexpectedEof)
-            } else if probability < 0.2 {
-                Error::from(ErrorKind::PermissionDenied)
-            } else {
-                // The system cannot find the file specified. (os error 2)
-                Error::from_raw_os_error(2)
-            };
+        let generation = self.disk.generation();
+        self.disk.check(generation)?;
+        // opening a directory succeeds on some OSes, it never does in simulation.
+        // A directory not seen yet is recorded when the OS hands a directory back
+        let result = if let Err(error) = self.disk.check_path(path) {
+            Err(error)
+        } else if self.disk.is_directory(path) {
+            Err(is_a_directory())
+        } else {
+            std::fs::File::open(path).and_then(|file| {
+                if file.metadata()?.is_dir() {
+                    self.disk.add_directory(path);
+                    return Err(is_a_directory());
+                }
+                Ok(file)
+            })
+        };
+
+        let cached = result.is_ok() && self.page_cache_access(path);
+        let latency = self.buggify_io_error(cached).map_err(|error| {
             tracing::info!("buggified open file {:?}: {:?}", path, error);
-            return io::Result::Err(error);
-        }
-        let result = std::fs::File::open(path);
+            error
+        })?;
+        self.io_latency(latency).await;
+        self.disk.check(generation)?;
 
-        let wait_duration = Duration::from_millis(self.random.random_between(300u64..2000u64));
-        DeterministicTimer::wait_with_reactor(
-            self.time.clone(),
-            self.reactor.clone(),
-            wait_duration,
-        )
-        .await;
+        result.map(|file| self.simulated_file(file, path))
+    }
 
-        match result {
-            Ok(file) => Ok(SimulatedFile::new(file).into()),
-            Err(error) => Err(error),
+    async fn open_timeout(&mut self, path: &Path, duration: Duration) -> io::Result<File> {
+        let (time, reactor) = (self.time.clone(), self.reactor.clone());
+        // on a tie, the open wins
+        match timeout(time, reactor, duration, self.open(path)).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::info!("open file {:?} timed out after {:?}", path, duration);
+                Err(Error::from(ErrorKind::TimedOut))
+            }
         }
     }
 
+    async fn create(&mut self, path: &Path) -> io::Result<File> {
+        let generation = self.disk.generation();
+        self.disk.check(generation)?;
+        let latency = self.buggify_io_error(false).map_err(|error| {
+            tracing::info!("buggified create file {:?}: {:?}", path, error);
+            error
+        })?;
+        let existed = path.exists() && !self.disk.is_lost(path);
+        let result = create_file(path);
+        if result.is_ok() {
+            if !existed {
+                self.disk.created(path);
+            }
+            if let Some(parent) = path.parent() {
+                self.disk.add_directory(parent);
+            }
+        }
+
+        self.io_latency(latency).await;
+        self.disk.check(generation)?;
+
+        result.map(|file| self.simulated_file(file, path))
+    }
+
+    async fn fsync_dir(&mut self, path: &Path) -> io::Result<()> {
+        let generation = self.disk.generation();
+        self.disk.check(generation)?;
+        let latency = self.buggify_io_error(false).map_err(|error| {
+            tracing::info!("buggified sync dir {:?}: {:?}", path, error);
+            error
+        })?;
+        // the entries are only kept in memory, but the errors should be the ones of the OS
+        let result = std::fs::read_dir(path).map(|_| ());
+
+        self.io_latency(latency).await;
+        self.disk.check(generation)?;
+
+        result.map(|()| {
+            self.disk.add_directory(path);
+            self.disk.sync_dir(path)
+        })
+    }
+
+    async fn read_to_string(&mut self, path: &Path) -> io::Result<String> {
+        let generation = self.disk.generation();
+        self.disk.check(generation)?;
+        let latency = self.buggify_io_error(false).map_err(|error| {
+            tracing::info!("buggified read file {:?}: {:?}", path, error);
+            error
+        })?;
+        let result = self
+            .disk
+            .check_path(path)
+            .and_then(|()| std::fs::read_to_string(path));
+
+        self.io_latency(latency).await;
+        self.disk.check(generation)?;
+
+        result
+    }
+
+    async fn read_dir(&mut self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let generation = self.disk.generation();
+        self.disk.check(generation)?;
+        let latency = self.buggify_io_error(false).map_err(|error| {
+            tracing::info!("buggified read dir {:?}: {:?}", path, error);
+            error
+        })?;
+        // sorting the entries, as the order returned by the OS is not deterministic
+        let result = std::fs::read_dir(path).and_then(|entries| {
+            let mut paths = entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<PathBuf>>>()?;
+            paths.retain(|path| !self.disk.is_lost(path));
+            paths.sort();
+            self.disk.add_directory(path);
+            Ok(paths)
+        });
+
+        self.io_latency(latency).await;
+        self.disk.check(generation)?;
+
+        result
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        DeterministicTimer::wait_with_reactor(self.time.clone(), self.reactor.clone(), duration)
+            .await;
+    }
+
     fn now(&self) -> Instant {
         self.time.now()
     }
+
+    fn system_now(&self) -> SystemTime {
+        self.time.system_now()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::deterministic::platform::SimulationPlatform;
-    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::fs::file::BitRot;
+    use crate::deterministic::platform::{
+        buggify_seed, SimulationPlatform, FAULT_CLOCK_SKEW, FAULT_FILE_REPLACED, FAULT_IO_ERROR,
+    };
+    use crate::deterministic::random::DeterministicRandom;
     use crate::deterministic::runtime::reactor::DeterministicReactor;
-    use crate::deterministic::runtime::task::Task;
+    use crate::deterministic::time::ClockSkew;
+    use crate::deterministic::trace::{ReplayDivergence, Trace};
+    use crate::file::FileTrait;
     use crate::platform::Platform;
-    use std::path::Path;
-    use std::time::Duration;
+    use crate::run::{run, run_on};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::io::{ErrorKind, SeekFrom};
+    use std::panic::AssertUnwindSafe;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, UNIX_EPOCH};
     use tracing::Level;
 
-    async fn example_task_open_file(reactor: DeterministicReactor) {
-        let mut platform = SimulationPlatform::new(42, reactor);
-        let start = platform.now();
-        let file_result = platform.open(Path::new("/etc/hosts")).await;
-        let end = platform.now();
+    /// using seed 42, the first open takes 817ms
+    const OPEN_LATENCY_42: Duration = Duration::from_millis(817);
 
-        assert!(file_result.is_ok(), "could not open /etc/hosts");
-        assert!(
-            start.lt(&end),
-            "simulated time did not moved: start={:?}, end={:?}",
-            start,
-            end
-        );
-        // using seed 42, time should have been moved by 817ms exactly
-        assert_eq!(
-            end.duration_since(start),
-            Duration::from_millis(817),
-            "None deterministic time found!"
-        );
+    /// creates a platform seeded with `seed`, on a reactor of its own
+    fn platform(seed: u64) -> SimulationPlatform {
+        SimulationPlatform::new(seed, DeterministicReactor::default())
     }
 
     #[test]
@@ -126,11 +555,182 @@ mod tests {
             .with_test_writer()
             .try_init();
 
-        let reactor = DeterministicReactor::default();
+        run(42, |mut platform| async move {
+            let start = platform.now();
+            let file_result = platform.open(Path::new("/etc/hosts")).await;
+            let end = platform.now();
 
-        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
-        executor.spawn(Task::new(example_task_open_file(reactor)));
-        executor.run();
+            assert!(file_result.is_ok(), "could not open /etc/hosts");
+            assert!(
+                start.lt(&end),
+                "simulated time did not moved: start={:?}, end={:?}",
+                start,
+                end
+            );
+            assert_eq!(
+                end.duration_since(start),
+                OPEN_LATENCY_42,
+                "None deterministic time found!"
+            );
+        });
+    }
+
+    #[test]
+    fn test_measure_async() {
+        run(42, |platform| async move {
+            let mut opening = platform.clone();
+            let (file, elapsed) = platform
+                .measure_async(opening.open(Path::new("/etc/hosts")))
+                .await;
+            assert!(file.is_ok(), "could not open /etc/hosts");
+            assert_eq!(elapsed, OPEN_LATENCY_42);
+
+            let (_, elapsed) = platform.measure_async(async {}).await;
+            assert_eq!(elapsed, Duration::ZERO);
+        });
+    }
+
+    #[test]
+    fn test_buggify_seed() {
+        // the buggifier is not drawing the values drawn by the random of the platform
+        let mut random = DeterministicRandom::new_with_seed(42);
+        let mut buggify_random = SmallRng::seed_from_u64(buggify_seed(42));
+        let draws: Vec<u64> = (0..10)
+            .map(|_| random.random_between(0..u64::MAX))
+            .collect();
+        let buggify_draws: Vec<u64> = (0..10)
+            .map(|_| buggify_random.gen_range(0..u64::MAX))
+            .collect();
+        assert!(draws.iter().all(|draw| !buggify_draws.contains(draw)));
+        assert_eq!(buggify_seed(42), buggify_seed(42));
+
+        // while both streams are reproducible
+        let record = || {
+            let platform = SimulationPlatform::new_recording(42, DeterministicReactor::default());
+            run_on(platform, |platform| async move {
+                let _ = open_twelve_times(platform.clone()).await;
+                platform.trace().expect("platform should be recording")
+            })
+        };
+        let trace = record();
+        assert!(trace.decisions.contains(&true));
+        assert_eq!(trace, record());
+    }
+
+    /// opens, reads and lists files many times, returns the number of injected faults
+    fn inject_faults(platform: SimulationPlatform) -> u64 {
+        run_on(platform, |mut platform| async move {
+            for _ in 0..100 {
+                let _ = platform.open(Path::new("/etc/hosts")).await;
+                let _ = platform.read_to_string(Path::new("/etc/hosts")).await;
+                let _ = platform.read_dir(Path::new("/etc")).await;
+            }
+            platform.buggifier.activation_count()
+        })
+    }
+
+    #[test]
+    fn test_fault_budget() {
+        assert!(inject_faults(platform(42)) > 1);
+        assert_eq!(inject_faults(platform(42).with_fault_budget(1)), 1);
+    }
+
+    #[test]
+    fn test_open_timeout() {
+        let open_timeout = |timeout| {
+            run(42, move |mut platform| async move {
+                let start = platform.now();
+                let file_result = platform
+                    .open_timeout(Path::new("/etc/hosts"), timeout)
+                    .await;
+                (file_result.map(|_| ()), platform.elapsed_since(start))
+            })
+        };
+
+        let timeout = Duration::from_millis(500);
+        match open_timeout(timeout) {
+            (Err(error), elapsed) => {
+                assert_eq!(error.kind(), ErrorKind::TimedOut);
+                assert_eq!(elapsed, timeout);
+            }
+            (Ok(_), _) => panic!("the open should have timed out"),
+        }
+
+        let (file_result, elapsed) = open_timeout(Duration::from_secs(1));
+        assert!(file_result.is_ok(), "could not open /etc/hosts");
+        assert_eq!(elapsed, OPEN_LATENCY_42);
+    }
+
+    #[test]
+    fn test_timeout_cancels_open() {
+        let platform = platform(42);
+        let reactor = platform.reactor();
+        let time = reactor.get_deterministic_time();
+        let simulation_start = time.now();
+        run_on(platform, |platform| async move {
+            let mut opener = platform.clone();
+            let start = platform.now();
+            let open = opener.open(Path::new("/etc/hosts"));
+            let result = platform.timeout(Duration::from_millis(500), open).await;
+            assert!(result.is_err(), "the open should have timed out");
+            assert_eq!(
+                reactor.pending_waits(),
+                0,
+                "the latency of the open is still pending"
+            );
+            assert_eq!(platform.elapsed_since(start), Duration::from_millis(500));
+        });
+        assert_eq!(
+            time.now().duration_since(simulation_start),
+            Duration::from_millis(500)
+        );
+    }
+
+    #[test]
+    fn test_system_now() {
+        run(42, |platform| async move {
+            let (start, system_start) = (platform.now(), platform.system_now());
+            // the simulation starts on 2020-01-01, whatever the real time is
+            assert_eq!(
+                system_start.duration_since(UNIX_EPOCH).unwrap(),
+                Duration::from_secs(1_577_836_800)
+            );
+            for _ in 0..3 {
+                platform.sleep(Duration::from_millis(1500)).await;
+                assert_eq!(
+                    platform.system_now().duration_since(system_start).unwrap(),
+                    platform.now().duration_since(start)
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_open_directory() {
+        let directory = test_directory("open-directory");
+
+        let path = directory.clone();
+        run(42, |mut platform| async move {
+            let start = platform.now();
+            let is_a_directory = |result: std::io::Result<_>| match result {
+                Err(error) => {
+                    error.kind() == ErrorKind::Other && error.to_string() == "is a directory"
+                }
+                Ok(_) => false,
+            };
+            assert!(is_a_directory(platform.open(&path).await));
+            // like for a file
+            assert_eq!(platform.elapsed_since(start), OPEN_LATENCY_42);
+
+            // the directory entries are known to the simulation once a file is created in them
+            let nested = path.join("nested");
+            std::fs::create_dir(&nested).unwrap();
+            platform.create(&nested.join("file")).await.unwrap();
+            assert!(platform.disk.is_directory(&nested));
+            assert!(is_a_directory(platform.open(&nested).await));
+        });
+
+        std::fs::remove_dir_all(directory).unwrap();
     }
 
     #[test]
@@ -140,20 +740,569 @@ mod tests {
             .with_test_writer()
             .try_init();
 
-        let reactor = DeterministicReactor::default();
+        let outcomes = run(42, open_twelve_times);
+        let opened: Vec<bool> = outcomes.iter().map(|(opened, _)| *opened).collect();
+        let mut expected = vec![true; 12];
+        expected[11] = false;
+        assert_eq!(opened, expected);
+    }
 
-        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
-        executor.spawn(Task::new(async move {
-            let mut platform = SimulationPlatform::new(42, reactor);
-            for i in 0..10 {
+    /// opens a file twice under seed 6, returns the outcome and duration of each open
+    fn open_twice_seeded(inject: bool) -> Vec<(bool, Duration)> {
+        let platform = platform(6);
+        if !inject {
+            platform.buggifier.disable_group(FAULT_IO_ERROR);
+        }
+        run_on(platform, |mut platform| async move {
+            let mut outcomes = vec![];
+            for _ in 0..2 {
+                let start = platform.now();
                 let file_result = platform.open(Path::new("/etc/hosts")).await;
-                if i == 8 {
-                    assert!(file_result.is_err());
-                } else {
-                    assert!(file_result.is_ok());
+                outcomes.push((file_result.is_ok(), platform.elapsed_since(start)));
+            }
+            outcomes
+        })
+    }
+
+    #[test]
+    fn test_open_draw_order() {
+        // using seed 6, the first open is buggified
+        let buggified = open_twice_seeded(true);
+        let healthy = open_twice_seeded(false);
+        assert_eq!(buggified[0], (false, Duration::ZERO));
+        assert!(healthy[0].0);
+        // the latency of the second open does not depend on the outcome of the first one
+        assert!(buggified[1].0);
+        assert_eq!(buggified[1], healthy[1]);
+    }
+
+    /// reads then opens a file, returns the outcome and duration of both operations
+    fn read_then_open(seed: u64, inject: bool) -> Vec<(bool, Duration)> {
+        let platform = platform(seed);
+        if !inject {
+            platform.buggifier.disable_group(FAULT_IO_ERROR);
+        }
+        run_on(platform, |mut platform| async move {
+            let path = Path::new("/etc/hosts");
+            let start = platform.now();
+            let read = platform.read_to_string(path).await.is_ok();
+            let read = (read, platform.elapsed_since(start));
+            let start = platform.now();
+            let opened = platform.open(path).await.is_ok();
+            vec![read, (opened, platform.elapsed_since(start))]
+        })
+    }
+
+    #[test]
+    fn test_io_draw_order() {
+        let mut buggified_reads = 0;
+        for seed in 0..200 {
+            let (buggified, healthy) = (read_then_open(seed, true), read_then_open(seed, false));
+            if !buggified[0].0 {
+                buggified_reads += 1;
+            }
+            // every operation draws the same values, whether it failed or not
+            if buggified[1].0 {
+                assert_eq!(buggified[1], healthy[1], "seed {}", seed);
+            }
+        }
+        assert!(buggified_reads > 0, "no read was buggified");
+    }
+
+    /// opens a file twelve times, returns the outcome and duration of each open
+    async fn open_twelve_times(mut platform: SimulationPlatform) -> Vec<(bool, Duration)> {
+        let mut outcomes = vec![];
+        for _ in 0..12 {
+            let start = platform.now();
+            let file_result = platform.open(Path::new("/etc/hosts")).await;
+            outcomes.push((file_result.is_ok(), platform.elapsed_since(start)));
+        }
+        outcomes
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let recording = SimulationPlatform::new_recording(42, DeterministicReactor::default());
+        let (outcomes, trace) = run_on(recording, |platform| async move {
+            let outcomes = open_twelve_times(platform.clone()).await;
+            let trace = platform.trace().expect("platform should be recording");
+            (outcomes, trace)
+        });
+        // the buggified open of seed 42
+        assert!(!outcomes[11].0);
+
+        // replaying from the serialized trace
+        let trace: Trace = trace.to_string().parse().expect("could not parse trace");
+        let replaying = SimulationPlatform::replay(&trace, DeterministicReactor::default());
+        assert!(replaying.trace().is_none());
+        assert_eq!(run_on(replaying, open_twelve_times), outcomes);
+    }
+
+    /// replays `trace` while opening a file `opens` times
+    fn replay_opens(trace: &Trace, opens: usize) -> Result<(), ReplayDivergence> {
+        let replaying = SimulationPlatform::replay(trace, DeterministicReactor::default());
+        run_on(replaying, |mut platform| async move {
+            for _ in 0..opens {
+                let _ = platform.open(Path::new("/etc/hosts")).await;
+            }
+            platform.check_replay()
+        })
+    }
+
+    #[test]
+    fn test_replay_divergence() {
+        let recording = SimulationPlatform::new_recording(42, DeterministicReactor::default());
+        let trace = run_on(recording, |platform| async move {
+            let _ = open_twelve_times(platform.clone()).await;
+            platform.trace().expect("platform should be recording")
+        });
+        let recorded = trace.draws.len();
+
+        assert_eq!(replay_opens(&trace, 12), Ok(()));
+
+        // the code changed and opens the file once more
+        match replay_opens(&trace, 13) {
+            Err(ReplayDivergence::Draws {
+                step, requested, ..
+            }) => {
+                assert_eq!(step, recorded);
+                assert!(requested > recorded);
+            }
+            other => panic!("expected a divergence, got {:?}", other),
+        }
+
+        // the code changed and opens the file once less
+        match replay_opens(&trace, 11) {
+            Err(ReplayDivergence::Draws {
+                step, requested, ..
+            }) => {
+                assert_eq!(step, requested);
+                assert!(requested < recorded);
+            }
+            other => panic!("expected a divergence, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_buggified_clock_drift() {
+        let drifted = (0..100).find_map(|seed| {
+            let mut platform = platform(seed);
+            (0..100)
+                .map(|_| platform.buggify_clock_drift(100))
+                .find(|drift| *drift != 0)
+                .map(|drift| (seed, drift))
+        });
+        let (seed, drift) = drifted.expect("no seed made the clock drift");
+        assert!((-100..=100).contains(&drift));
+
+        // same seed, same drift, and the clock keeps working
+        let mut platform = platform(seed);
+        let time = platform.reactor().get_deterministic_time();
+        while platform.buggify_clock_drift(100) == 0 {}
+        assert_eq!(platform.buggify_clock_drift(100), drift);
+        let start = time.now();
+        run_on(platform, |platform| async move {
+            platform.sleep(Duration::from_secs(3600)).await;
+        });
+        let elapsed = time.now().duration_since(start);
+        // the drifting clock slept an hour, the canonical one about as long
+        assert!(elapsed >= Duration::from_secs(3599) && elapsed <= Duration::from_secs(3601));
+    }
+
+    #[test]
+    fn test_buggified_clock_skew() {
+        let reactor = DeterministicReactor::default();
+        let time = reactor.get_deterministic_time();
+
+        let mut skewed = None;
+        for seed in 0..100 {
+            let mut platform = SimulationPlatform::new(seed, reactor.clone());
+            for _ in 0..100 {
+                let skew = platform.buggify_clock_skew(Duration::from_secs(1));
+                if skew != ClockSkew::default() {
+                    skewed = Some((seed, skew, platform.now()));
+                    break;
                 }
             }
+            if skewed.is_some() {
+                break;
+            }
+        }
+
+        let (seed, skew, now) = skewed.expect("no seed skewed the clock");
+        match skew {
+            ClockSkew::Ahead(offset) => assert_eq!(now.duration_since(time.now()), offset),
+            ClockSkew::Behind(offset) => assert_eq!(time.now().duration_since(now), offset),
+        }
+
+        // same seed, same skew
+        let mut platform = SimulationPlatform::new(seed, reactor);
+        let mut replayed = ClockSkew::default();
+        while replayed == ClockSkew::default() {
+            replayed = platform.buggify_clock_skew(Duration::from_secs(1));
+        }
+        assert_eq!(skew, replayed);
+    }
+
+    #[test]
+    fn test_page_cache() {
+        let caching = platform(42).with_page_cache(Duration::from_secs(60));
+        let latencies = run_on(caching, |mut platform| async move {
+            let mut latencies = vec![];
+            for _ in 0..2 {
+                let start = platform.now();
+                assert!(platform.open(Path::new("/etc/hosts")).await.is_ok());
+                latencies.push(platform.elapsed_since(start));
+            }
+            latencies
+        });
+
+        assert!(
+            latencies[0] >= Duration::from_millis(300),
+            "cold open was fast"
+        );
+        assert!(
+            latencies[1] < Duration::from_millis(10),
+            "cached open was slow"
+        );
+    }
+
+    /// creates an empty directory for a test
+    fn test_directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("circus-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("could not create test directory");
+        path
+    }
+
+    #[test]
+    fn test_read_to_string_and_read_dir() {
+        let directory = test_directory("read");
+        std::fs::write(directory.join("a.txt"), "hello").unwrap();
+        std::fs::write(directory.join("b.txt"), "world").unwrap();
+
+        let path = directory.clone();
+        run(42, |mut platform| async move {
+            let start = platform.now();
+
+            let content = platform.read_to_string(&path.join("a.txt")).await;
+            assert_eq!(content.expect("could not read file"), "hello");
+
+            let entries = platform.read_dir(&path).await;
+            assert_eq!(
+                entries.expect("could not read dir"),
+                vec![path.join("a.txt"), path.join("b.txt")]
+            );
+            assert!(start < platform.now(), "simulated time did not moved");
+        });
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_since_start() {
+        run(42, |mut platform| async move {
+            let before_open = platform.now();
+            assert_eq!(platform.since_start(), Duration::ZERO);
+
+            assert!(platform.open(Path::new("/etc/hosts")).await.is_ok());
+            assert_eq!(platform.since_start(), OPEN_LATENCY_42);
+            assert_eq!(platform.elapsed_since(before_open), OPEN_LATENCY_42);
+        });
+    }
+
+    #[test]
+    fn test_crash() {
+        let directory = test_directory("crash");
+        let path = directory.join("log");
+
+        let (log, parent) = (path.clone(), directory.clone());
+        run(42, |mut platform| async move {
+            let mut file = platform.create(&log).await.expect("could not create file");
+            file.write(b"synced").await.unwrap();
+            file.sync_all().await.unwrap();
+            platform.fsync_dir(&parent).await.unwrap();
+            file.write(b" and lost").await.unwrap();
+
+            platform.crash();
+            assert!(file.write(b"!").await.is_err());
+            assert!(file.sync_all().await.is_err());
+            assert!(platform.open(&log).await.is_err());
+            drop(file);
+
+            platform.restart();
+            let content = platform.read_to_string(&log).await;
+            assert_eq!(content.expect("could not read file"), "synced");
+            // the lost bytes are not seen through a simulated file either
+            let mut file = platform.open(&log).await.expect("could not open file");
+            assert_eq!(file.seek(SeekFrom::End(0)).await.unwrap(), 6);
+            file.seek(SeekFrom::Start(0)).await.unwrap();
+            let mut buffer = [0; 16];
+            let read = file.read(&mut buffer).await.unwrap();
+            assert_eq!(&buffer[..read], b"synced");
+        });
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_crash_before_fsync_dir() {
+        let directory = test_directory("crash-before-fsync-dir");
+        let (durable, lost) = (directory.join("durable"), directory.join("lost"));
+
+        let (parent, created, lost_path) = (directory.clone(), durable.clone(), lost.clone());
+        run(42, |mut platform| async move {
+            platform
+                .create(&created)
+                .await
+                .expect("could not create file");
+            platform.fsync_dir(&parent).await.unwrap();
+            // synced, but its directory entry is not
+            let mut file = platform
+                .create(&lost_path)
+                .await
+                .expect("could not create file");
+            file.write(b"synced").await.unwrap();
+            file.sync_all().await.unwrap();
+            drop(file);
+
+            platform.crash();
+            platform.restart();
+            assert!(platform.open(&created).await.is_ok());
+            let error = platform
+                .open(&lost_path)
+                .await
+                .err()
+                .expect("file was not lost");
+            assert_eq!(error.kind(), ErrorKind::NotFound);
+            let error = platform.read_to_string(&lost_path).await.unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::NotFound);
+            let entries = platform.read_dir(&parent).await.unwrap();
+            assert_eq!(entries, vec![created.clone()]);
+
+            // until it is created again
+            platform.create(&lost_path).await.unwrap();
+            assert!(platform.open(&lost_path).await.is_ok());
+        });
+
+        // the crash is only simulated, the files of the host are kept
+        assert!(durable.exists());
+        assert!(lost.exists());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_write_all_short_writes() {
+        let directory = test_directory("short-writes");
+        let path = directory.join("payload");
+        let payload: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+
+        let (written, expected) = (path.clone(), payload.clone());
+        let shortening = platform(42).with_short_writes(1.0);
+        run_on(shortening, |mut platform| async move {
+            let mut file = platform
+                .create(&written)
+                .await
+                .expect("could not create file");
+            assert!(file.write(&expected).await.unwrap() < expected.len());
+            file.sync_all().await.unwrap();
+
+            let mut file = platform
+                .create(&written)
+                .await
+                .expect("could not create file");
+            platform.write_all(&mut file, &expected).await.unwrap();
+            file.sync_all().await.unwrap();
+        });
+
+        assert_eq!(std::fs::read(&path).unwrap(), payload);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_seek() {
+        let directory = test_directory("seek");
+        let path = directory.join("payload");
+
+        let created = path.clone();
+        let slice = run(42, |mut platform| async move {
+            let mut file = platform
+                .create(&created)
+                .await
+                .expect("could not create file");
+            platform.write_all(&mut file, b"hello world").await.unwrap();
+
+            // the written bytes are not synced yet, reads are seeing them
+            assert_eq!(file.seek(SeekFrom::Start(6)).await.unwrap(), 6);
+            let mut buffer = [0; 16];
+            let read = file.read(&mut buffer).await.unwrap();
+            let slice = buffer[..read].to_vec();
+
+            let error = file.seek(SeekFrom::Current(-12)).await.unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+            // a failed seek does not move the position
+            assert_eq!(file.seek(SeekFrom::Current(0)).await.unwrap(), 11);
+
+            // overwriting from the end of the file
+            assert_eq!(file.seek(SeekFrom::End(-5)).await.unwrap(), 6);
+            file.write(b"there").await.unwrap();
+            file.sync_all().await.unwrap();
+            slice
+        });
+
+        assert_eq!(slice, b"world".to_vec());
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello there");
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_crash_inflight() {
+        run(42, |platform| async move {
+            let (mut opening, mut crashing) = (platform.clone(), platform);
+            let open = async move { opening.open(Path::new("/etc/hosts")).await.is_ok() };
+            let crash = async move {
+                crashing.sleep(Duration::from_millis(100)).await;
+                crashing.crash();
+            };
+            // the crash happens during the latency of the open
+            let (opened, ()) = futures::join!(open, crash);
+            assert!(!opened);
+        });
+    }
+
+    /// reads the file through a platform with the given seed and bit rot,
+    /// after drawing `draws` random values from the platform
+    fn read_with_bit_rot(seed: u64, path: PathBuf, bit_rot: BitRot, draws: usize) -> Vec<u8> {
+        run_on(
+            platform(seed).with_bit_rot(bit_rot),
+            |mut platform| async move {
+                let mut random = platform.random();
+                for _ in 0..draws {
+                    random.random_between(0..u64::MAX);
+                }
+                let mut file = platform.open(&path).await.expect("could not open file");
+                let (mut content, mut buf) = (vec![], [0u8; 16]);
+                loop {
+                    let read = file.read(&mut buf).await.expect("could not read file");
+                    if read == 0 {
+                        break content;
+                    }
+                    content.extend_from_slice(&buf[..read]);
+                }
+            },
+        )
+    }
+
+    #[test]
+    fn test_bit_rot() {
+        let directory = test_directory("bit-rot");
+        let path = directory.join("payload");
+        let payload: Vec<u8> = (0..64).collect();
+        std::fs::write(&path, &payload).unwrap();
+
+        // the first read of 16 bytes flips a bit, the site then already fired
+        let bit_rot = BitRot {
+            probability: 1.0,
+            per_bytes: 16,
+        };
+        let corrupted = read_with_bit_rot(42, path.clone(), bit_rot, 0);
+        assert_eq!(corrupted.len(), payload.len());
+        let differences: Vec<usize> = (0..payload.len())
+            .filter(|i| payload[*i] != corrupted[*i])
+            .collect();
+        assert_eq!(differences.len(), 1, "expected a single corrupted byte");
+        assert!(differences[0] < 16);
+        assert_eq!(
+            (payload[differences[0]] ^ corrupted[differences[0]]).count_ones(),
+            1
+        );
+
+        // corruption is reproducible, and does not depend on the other draws of the platform
+        assert_eq!(read_with_bit_rot(42, path.clone(), bit_rot, 0), corrupted);
+        assert_eq!(read_with_bit_rot(42, path.clone(), bit_rot, 3), corrupted);
+        assert_ne!(read_with_bit_rot(7, path.clone(), bit_rot, 0), corrupted);
+
+        // the probability of a read is capped, however many bytes are read
+        let rotten = BitRot {
+            probability: 0.5,
+            per_bytes: 1,
+        };
+        assert_eq!(rotten.read_probability(16), 0.5);
+        assert_eq!(rotten.read_probability(0), 0.0);
+        assert_eq!(bit_rot.read_probability(4), 0.25);
+
+        // no corruption without bit rot
+        let no_bit_rot = BitRot {
+            probability: 0.0,
+            per_bytes: 16,
+        };
+        assert_eq!(read_with_bit_rot(42, path, no_bit_rot, 0), payload);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    /// opens a file `opens` times with seed 42, returns the platform afterwards
+    fn open_with_seed_42(opens: usize) -> SimulationPlatform {
+        run(42, |mut platform| async move {
+            for _ in 0..opens {
+                let _ = platform.open(Path::new("/etc/hosts")).await;
+            }
+            platform
+        })
+    }
+
+    #[test]
+    fn test_assert_injected() {
+        // the 12th open is buggified with seed 42
+        let platform = open_with_seed_42(12);
+        platform.assert_injected(FAULT_IO_ERROR);
+        let skew = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            platform.assert_injected(FAULT_CLOCK_SKEW)
         }));
-        executor.run();
+        assert!(skew.is_err(), "the clock was never skewed");
+    }
+
+    #[test]
+    #[should_panic(expected = "was never injected")]
+    fn test_assert_injected_missing() {
+        open_with_seed_42(11).assert_injected(FAULT_IO_ERROR);
+    }
+
+    /// opens `/etc/hosts` twice, replacing it in between if `replace`, returns both identities
+    async fn open_twice(mut platform: SimulationPlatform, replace: bool) -> Option<(u64, u64)> {
+        let path = Path::new("/etc/hosts");
+        let first = platform.open(path).await.ok()?.file_id().ok()?;
+        if replace {
+            platform.replace_file(path);
+        }
+        let second = platform.open(path).await.ok()?.file_id().ok()?;
+        Some((first, second))
+    }
+
+    fn file_ids(platform: SimulationPlatform, replace: bool) -> Option<(u64, u64)> {
+        run_on(platform, |platform| open_twice(platform, replace))
+    }
+
+    #[test]
+    fn test_file_id() {
+        let (first, second) = file_ids(platform(42), false).unwrap();
+        assert_eq!(first, second, "same path, same file");
+        let (first, second) = file_ids(platform(42), true).unwrap();
+        assert_ne!(first, second, "the file was replaced");
+
+        // a seed where buggify replaces the file behind the second open
+        let seed = (0..1000)
+            .find(|seed| {
+                let ids = file_ids(platform(*seed).with_file_replacements(), false);
+                matches!(ids, Some((first, second)) if first != second)
+            })
+            .expect("no seed replaced the file");
+        let replacing = platform(seed).with_file_replacements();
+        let ids = file_ids(replacing.clone(), false);
+        replacing.assert_injected(FAULT_FILE_REPLACED);
+        assert_eq!(
+            ids,
+            file_ids(platform(seed).with_file_replacements(), false)
+        );
     }
 }
@@ -1,52 +1,539 @@
 //! Deterministic randomness
-use std::ops::Range;
+use std::cell::RefCell;
+use std::ops::{Range, RangeInclusive};
 
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
 use rand::distributions::uniform::SampleUniform;
-use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// A source of randomness that can be seeded to become deterministic
 #[derive(Clone, Debug)]
 pub struct DeterministicRandom {
-    inner: Arc<Mutex<rand::rngs::SmallRng>>,
+    inner: Arc<Mutex<Source>>,
+}
+
+/// The algorithm drawing the random values
+enum Generator {
+    Small(SmallRng),
+    Stable(Pcg32),
+    // an algorithm chosen by the user, see `DeterministicRandom::with_rng`
+    Custom {
+        rng: Box<dyn RngCore + Send>,
+        seeded: fn(u64) -> Box<dyn RngCore + Send>,
+    },
+}
+
+impl Generator {
+    /// returns a generator of the same algorithm, seeded by `seed`
+    fn reseeded(&self, seed: u64) -> Generator {
+        match self {
+            Generator::Small(_) => Generator::Small(SmallRng::seed_from_u64(seed)),
+            Generator::Stable(_) => Generator::Stable(Pcg32::new(seed, Pcg32::STREAM)),
+            Generator::Custom { seeded, .. } => Generator::Custom {
+                rng: seeded(seed),
+                seeded: *seeded,
+            },
+        }
+    }
+
+    /// returns a custom generator of the algorithm `R`, seeded by `seed`
+    fn custom<R: SeedableRng + RngCore + Send + 'static>(seed: u64) -> Generator {
+        fn seeded<R: SeedableRng + RngCore + Send + 'static>(seed: u64) -> Box<dyn RngCore + Send> {
+            Box::new(R::seed_from_u64(seed))
+        }
+        Generator::Custom {
+            rng: seeded::<R>(seed),
+            seeded: seeded::<R>,
+        }
+    }
+}
+
+impl fmt::Debug for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Generator::Small(rng) => f.debug_tuple("Small").field(rng).finish(),
+            Generator::Stable(rng) => f.debug_tuple("Stable").field(rng).finish(),
+            Generator::Custom { .. } => f.write_str("Custom"),
+        }
+    }
+}
+
+impl RngCore for Generator {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            Generator::Small(rng) => rng.next_u32(),
+            Generator::Stable(rng) => rng.next_u32(),
+            Generator::Custom { rng, .. } => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            Generator::Small(rng) => rng.next_u64(),
+            Generator::Stable(rng) => rng.next_u64(),
+            Generator::Custom { rng, .. } => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            Generator::Small(rng) => rng.fill_bytes(dest),
+            Generator::Stable(rng) => rng.fill_bytes(dest),
+            Generator::Custom { rng, .. } => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// PCG-XSH-RR 64/32, as described in <https://www.pcg-random.org/>. It is implemented here,
+/// so its output does not depend on the version of `rand`.
+#[derive(Debug)]
+struct Pcg32 {
+    state: u64,
+    increment: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+    // the stream used by `DeterministicRandom::new_stable`
+    const STREAM: u64 = 0xda3e_39cb_94b9_5bdb;
+
+    /// seeds the generator like `pcg32_srandom_r` of the reference implementation
+    fn new(seed: u64, stream: u64) -> Self {
+        let mut pcg = Pcg32 {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        pcg.step();
+        pcg.state = pcg.state.wrapping_add(seed);
+        pcg.step();
+        pcg
+    }
+
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+    }
+}
+
+impl RngCore for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.step();
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        xorshifted.rotate_right((state >> 59) as u32)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = u64::from(self.next_u32());
+        let high = u64::from(self.next_u32());
+        (high << 32) | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Where the random values are coming from
+#[derive(Debug)]
+enum Source {
+    Live(Generator),
+    // every value drawn is recorded
+    Recording(Generator, Vec<u64>),
+    // values are read from a recording, `requested` counts the values asked so far.
+    // Once the recording is exhausted, values are drawn from `fallback`
+    Replaying {
+        draws: Vec<u64>,
+        requested: usize,
+        fallback: SmallRng,
+    },
+}
+
+impl Source {
+    fn record(&mut self, value: u64) {
+        if let Source::Recording(_, draws) = self {
+            draws.push(value);
+        }
+    }
+
+    /// returns the next replayed value
+    fn replayed(&mut self) -> u64 {
+        match self {
+            Source::Replaying {
+                draws,
+                requested,
+                fallback,
+            } => {
+                *requested += 1;
+                match draws.get(*requested - 1) {
+                    Some(value) => *value,
+                    None => {
+                        if *requested == draws.len() + 1 {
+                            tracing::warn!("the replayed trace has no random values left");
+                        }
+                        fallback.next_u64()
+                    }
+                }
+            }
+            _ => unreachable!("the source is not replaying"),
+        }
+    }
+}
+
+impl RngCore for Source {
+    fn next_u32(&mut self) -> u32 {
+        let value = match self {
+            Source::Live(rng) => return rng.next_u32(),
+            Source::Recording(rng, _) => rng.next_u32(),
+            Source::Replaying { .. } => return self.replayed() as u32,
+        };
+        self.record(value as u64);
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = match self {
+            Source::Live(rng) => return rng.next_u64(),
+            Source::Recording(rng, _) => rng.next_u64(),
+            Source::Replaying { .. } => return self.replayed(),
+        };
+        self.record(value);
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            Source::Live(rng) => rng.fill_bytes(dest),
+            // like `SmallRng`, filling from successive `u64`s
+            _ => {
+                for chunk in dest.chunks_mut(8) {
+                    let bytes = self.next_u64().to_le_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+            }
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
 }
 
 impl DeterministicRandom {
     /// create a deterministic random given a seed
     pub fn new_with_seed(seed: u64) -> Self {
+        Self::from_source(Source::Live(Generator::Small(SmallRng::seed_from_u64(
+            seed,
+        ))))
+    }
+
+    /// Create a deterministic random given a seed, drawing from an algorithm implemented by
+    /// this crate instead of `SmallRng`. `SmallRng` is fast, but its algorithm may change with
+    /// any version of `rand`, changing what every recorded seed produces. The values drawn by
+    /// this random only depend on the seed, so a corpus of seeds stays valid across upgrades.
+    /// It is slower than `SmallRng`, and values derived with `rand`'s samplers, such as
+    /// [`DeterministicRandom::random_between`], still depend on `rand` keeping them stable.
+    pub fn new_stable(seed: u64) -> Self {
+        Self::from_source(Source::Live(Generator::Stable(Pcg32::new(
+            seed,
+            Pcg32::STREAM,
+        ))))
+    }
+
+    /// Create a deterministic random given a seed, drawing from the algorithm `R` instead of
+    /// `SmallRng`, such as `rand_chacha::ChaCha8Rng`:
+    /// `DeterministicRandom::with_rng::<ChaCha8Rng>(42)`. [`DeterministicRandom::reseed`]
+    /// keeps using `R`.
+    pub fn with_rng<R>(seed: u64) -> Self
+    where
+        R: SeedableRng + RngCore + Send + 'static,
+    {
+        Self::from_source(Source::Live(Generator::custom::<R>(seed)))
+    }
+
+    /// create a deterministic random given a seed, recording every value drawn,
+    /// see [`DeterministicRandom::recorded`]
+    pub fn new_recording(seed: u64) -> Self {
+        Self::from_source(Source::Recording(
+            Generator::Small(SmallRng::seed_from_u64(seed)),
+            vec![],
+        ))
+    }
+
+    /// create a deterministic random drawing the values previously recorded, in order.
+    /// Once every recorded value has been replayed, values are drawn from a fixed seed,
+    /// see [`DeterministicRandom::replay_progress`] to detect it.
+    pub fn new_replaying(draws: Vec<u64>) -> Self {
+        Self::from_source(Source::Replaying {
+            draws,
+            requested: 0,
+            fallback: SmallRng::seed_from_u64(0),
+        })
+    }
+
+    fn from_source(source: Source) -> Self {
         DeterministicRandom {
-            inner: Arc::new(Mutex::new(rand::rngs::SmallRng::seed_from_u64(seed))),
+            inner: Arc::new(Mutex::new(source)),
         }
     }
-    /// generate a random value between the range
+
+    /// Restarts the stream from `seed`, like a new random created with the same algorithm,
+    /// to start a new deterministic phase of a run, such as after a crash. Unlike creating a new
+    /// random, every clone of this random is reseeded. A recording random keeps recording,
+    /// and a replaying random keeps replaying, only the values drawn once the recording is
+    /// exhausted are restarting from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        match &mut *self.inner.lock() {
+            Source::Live(rng) | Source::Recording(rng, _) => *rng = rng.reseeded(seed),
+            Source::Replaying { fallback, .. } => *fallback = SmallRng::seed_from_u64(seed),
+        }
+    }
+
+    /// returns the values drawn so far by a recording random, or `None` if it is not recording
+    pub fn recorded(&self) -> Option<Vec<u64>> {
+        match &*self.inner.lock() {
+            Source::Recording(_, draws) => Some(draws.clone()),
+            _ => None,
+        }
+    }
+
+    /// returns how many values were recorded, and how many were asked so far while
+    /// replaying them, or `None` if the random is not replaying
+    pub fn replay_progress(&self) -> Option<(usize, usize)> {
+        match &*self.inner.lock() {
+            Source::Replaying {
+                draws, requested, ..
+            } => Some((draws.len(), *requested)),
+            _ => None,
+        }
+    }
+    /// generate a random value between the range, excluding its end.
+    /// An empty range returns its start instead of panicking.
     pub fn random_between<T: SampleUniform + PartialOrd>(&mut self, range: Range<T>) -> T {
+        if range.is_empty() {
+            return range.start;
+        }
+        let mut rng = self.inner.lock();
+        (*rng).gen_range(range)
+    }
+
+    /// generate a random value between the range, including its end.
+    /// An empty range, where the start is after the end, returns its start instead of panicking.
+    pub fn random_inclusive<T: SampleUniform + PartialOrd>(
+        &mut self,
+        range: RangeInclusive<T>,
+    ) -> T {
+        if range.is_empty() {
+            return range.into_inner().0;
+        }
         let mut rng = self.inner.lock();
         (*rng).gen_range(range)
     }
 
+    /// generate a random duration between the range, excluding its end, with a nanosecond
+    /// precision. Durations beyond `u64::MAX` nanoseconds, about 584 years, are saturated.
+    /// An empty range returns its start instead of panicking.
+    pub fn random_duration(&mut self, range: Range<Duration>) -> Duration {
+        let nanos = |duration: Duration| u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        let (start, end) = (nanos(range.start), nanos(range.end));
+        if start >= end {
+            return range.start;
+        }
+        Duration::from_nanos(self.random_between(start..end))
+    }
+
+    /// perturb `value` by up to `pct` of itself: it is multiplied by a factor between
+    /// `1 - pct` and `1 + pct`, both included. Handy to add noise around a baseline.
+    pub fn jitter(&mut self, value: f64, pct: f64) -> f64 {
+        let pct = pct.abs();
+        value * self.random_inclusive(1.0 - pct..=1.0 + pct)
+    }
+
     /// generate a random boolean given a probability
     pub fn random_boolean(&mut self, probability: f64) -> bool {
         let mut rng = self.inner.lock();
         (*rng).gen_bool(probability)
     }
 
+    /// generate the next `n` booleans given a probability, drawing exactly what `n` calls
+    /// to [`DeterministicRandom::random_boolean`] would. Useful to log the faults a seed
+    /// is about to produce.
+    pub fn flips(&mut self, n: usize, probability: f64) -> Vec<bool> {
+        let mut rng = self.inner.lock();
+        (0..n).map(|_| (*rng).gen_bool(probability)).collect()
+    }
+
     /// generate a float between 0 and 1
     pub fn random_01(&mut self) -> f32 {
         self.random_between(0_f32..1_f32)
     }
+
+    /// choose `k` distinct indices in `[0, n)`, using a partial Fisher–Yates shuffle.
+    /// Every index is returned if `k` is greater than `n`.
+    pub fn sample_indices(&mut self, n: usize, k: usize) -> Vec<usize> {
+        let k = k.min(n);
+        let mut indices: Vec<usize> = (0..n).collect();
+        let mut rng = self.inner.lock();
+        for i in 0..k {
+            let j = (*rng).gen_range(i..n);
+            indices.swap(i, j);
+        }
+        indices.truncate(k);
+        indices
+    }
+
+    /// choose an index given the cumulative weights of a discrete distribution, by searching
+    /// the bucket where a uniform draw in `[0, total)` lands. `cumulative` must be non-decreasing.
+    ///
+    /// # Panics
+    /// Panics if `cumulative` is empty or if its total weight is not positive.
+    pub fn sample_cumulative(&mut self, cumulative: &[f64]) -> usize {
+        let total = *cumulative.last().expect("cumulative weights are empty");
+        assert!(total > 0.0, "total weight must be positive");
+        let draw = self.random_between(0.0..total);
+        cumulative
+            .partition_point(|weight| *weight <= draw)
+            .min(cumulative.len() - 1)
+    }
+
+    /// returns a handle implementing [`rand::Rng`] over the inner random source,
+    /// allowing the use of `rand`'s distributions and samplers in a deterministic way.
+    /// The inner source is locked until the handle is dropped.
+    pub fn rng(&mut self) -> RngHandle<'_> {
+        RngHandle {
+            guard: self.inner.lock(),
+        }
+    }
+}
+
+thread_local! {
+    // the random installed for the simulation running on this thread
+    static CURRENT: RefCell<Option<DeterministicRandom>> = RefCell::new(None);
+}
+
+impl DeterministicRandom {
+    /// installs this random as the source of [`sim_rng`] on the current thread,
+    /// until the returned guard is dropped
+    pub fn install(&self) -> InstallGuard {
+        let previous = CURRENT.with(|current| current.borrow_mut().replace(self.clone()));
+        InstallGuard { previous }
+    }
+}
+
+/// Guard returned by [`DeterministicRandom::install`], restoring the previous random on drop
+#[derive(Debug)]
+pub struct InstallGuard {
+    previous: Option<DeterministicRandom>,
+}
+
+impl Drop for InstallGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Returns a random number generator to use instead of `rand::thread_rng`. During a simulation,
+/// it draws from the [`DeterministicRandom`] installed on the thread, so the values are
+/// reproducible with the seed. Otherwise, it falls back to `rand::thread_rng`.
+pub fn sim_rng() -> SimRng {
+    SimRng { _private: () }
+}
+
+/// Random number generator returned by [`sim_rng`]
+#[derive(Debug, Clone)]
+pub struct SimRng {
+    _private: (),
+}
+
+impl SimRng {
+    fn with_rng<T>(&self, f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        let current = CURRENT.with(|current| current.borrow().clone());
+        match current {
+            Some(mut random) => f(&mut random.rng()),
+            None => f(&mut rand::thread_rng()),
+        }
+    }
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        self.with_rng(|rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.with_rng(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.with_rng(|rng| rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.with_rng(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+/// A locked view over the random source of a [`DeterministicRandom`]
+#[derive(Debug)]
+pub struct RngHandle<'a> {
+    guard: MutexGuard<'a, Source>,
+}
+
+impl RngCore for RngHandle<'_> {
+    fn next_u32(&mut self) -> u32 {
+        self.guard.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.guard.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.guard.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.guard.try_fill_bytes(dest)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::deterministic::random::DeterministicRandom;
+    use crate::deterministic::random::{DeterministicRandom, Pcg32};
+    use rand::distributions::{Distribution, Uniform};
+    use rand::seq::SliceRandom;
+    use rand::{Rng, RngCore, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::BTreeSet;
+    use std::time::Duration;
 
     #[test]
     fn deterministic_random() {
         for seed in 0..9999 {
             let mut a = DeterministicRandom::new_with_seed(seed);
             let mut b = DeterministicRandom::new_with_seed(seed);
-            for range in vec![0.0..1.0, 0.0..42.0, 0.0..999.0] {
+            for range in [0.0..1.0, 0.0..42.0, 0.0..999.0] {
                 for _ in 0..999 {
                     let first: f64 = a.random_between(range.clone());
                     let second: f64 = b.random_between(range.clone());
@@ -57,4 +544,286 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn rng_handle() {
+        let distribution = Uniform::new(0u64, 1000u64);
+        for seed in 0..100 {
+            let mut a = DeterministicRandom::new_with_seed(seed);
+            let mut b = DeterministicRandom::new_with_seed(seed);
+            for _ in 0..100 {
+                assert_eq!(
+                    distribution.sample(&mut a.rng()),
+                    distribution.sample(&mut b.rng())
+                );
+            }
+
+            let mut items: Vec<u32> = (0..10).collect();
+            assert_eq!(items.choose(&mut a.rng()), items.choose(&mut b.rng()));
+            items.shuffle(&mut a.rng());
+            let mut other: Vec<u32> = (0..10).collect();
+            other.shuffle(&mut b.rng());
+            assert_eq!(items, other);
+
+            // the handle is sharing the stream with the other methods
+            assert_eq!(a.random_between(0..100), b.random_between(0..100));
+        }
+    }
+
+    #[test]
+    fn sample_cumulative() {
+        // weights of 1, 0, 3 and 6
+        let cumulative = [1.0, 1.0, 4.0, 10.0];
+        let mut counts = [0; 4];
+        for seed in 0..100 {
+            let mut a = DeterministicRandom::new_with_seed(seed);
+            let mut b = DeterministicRandom::new_with_seed(seed);
+            for _ in 0..100 {
+                let index = a.sample_cumulative(&cumulative);
+                assert_eq!(index, b.sample_cumulative(&cumulative));
+                counts[index] += 1;
+            }
+        }
+
+        assert_eq!(counts[1], 0, "empty bucket was chosen");
+        assert!(counts[0] > 500 && counts[0] < 1500, "{:?}", counts);
+        assert!(counts[2] > 2500 && counts[2] < 3500, "{:?}", counts);
+        assert!(counts[3] > 5500 && counts[3] < 6500, "{:?}", counts);
+
+        let mut random = DeterministicRandom::new_with_seed(42);
+        assert_eq!(random.sample_cumulative(&[0.0, 2.0]), 1);
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let mut recording = DeterministicRandom::new_recording(42);
+        let mut live = DeterministicRandom::new_with_seed(42);
+        let drawn: Vec<u64> = (0..100)
+            .map(|_| recording.random_between(0..1000))
+            .collect();
+        let expected: Vec<u64> = (0..100).map(|_| live.random_between(0..1000)).collect();
+        assert_eq!(drawn, expected, "recording should not change the values");
+        let mut bytes = [0u8; 13];
+        recording.rng().fill_bytes(&mut bytes);
+
+        let draws = recording.recorded().expect("should be recording");
+        assert!(live.recorded().is_none());
+
+        let mut replaying = DeterministicRandom::new_replaying(draws);
+        let replayed: Vec<u64> = (0..100)
+            .map(|_| replaying.random_between(0..1000))
+            .collect();
+        assert_eq!(replayed, drawn);
+        let mut replayed_bytes = [0u8; 13];
+        replaying.rng().fill_bytes(&mut replayed_bytes);
+        assert_eq!(replayed_bytes, bytes);
+    }
+
+    #[test]
+    fn ranges() {
+        let mut random = DeterministicRandom::new_with_seed(42);
+        for _ in 0..1000 {
+            let value = random.random_between(10..20);
+            assert!((10..20).contains(&value));
+            let value = random.random_inclusive(10..=20);
+            assert!((10..=20).contains(&value));
+        }
+        assert!((0..1000).any(|_| random.random_inclusive(0..=1) == 1));
+
+        // degenerate ranges
+        assert_eq!(random.random_inclusive(7..=7), 7);
+        assert_eq!(random.random_between(7..7), 7);
+        #[allow(clippy::reversed_empty_ranges)]
+        {
+            assert_eq!(random.random_between(9..3), 9);
+            assert_eq!(random.random_inclusive(9..=3), 9);
+        }
+
+        let mut other = DeterministicRandom::new_with_seed(42);
+        let mut random = DeterministicRandom::new_with_seed(42);
+        assert_eq!(
+            other.random_inclusive(0..=99),
+            random.random_inclusive(0..=99)
+        );
+    }
+
+    #[test]
+    fn sim_rng() {
+        let draw = || {
+            let mut rng = crate::deterministic::random::sim_rng();
+            (0..10)
+                .map(|_| rng.gen_range(0..1000))
+                .collect::<Vec<u64>>()
+        };
+
+        let random = DeterministicRandom::new_with_seed(42);
+        let installed = {
+            let _guard = random.install();
+            draw()
+        };
+        let mut expected = DeterministicRandom::new_with_seed(42);
+        let expected: Vec<u64> = (0..10).map(|_| expected.rng().gen_range(0..1000)).collect();
+        assert_eq!(installed, expected);
+
+        // nested installations are restored
+        let outer = DeterministicRandom::new_with_seed(1);
+        let _outer_guard = outer.install();
+        {
+            let _inner_guard = DeterministicRandom::new_with_seed(42).install();
+            assert_eq!(draw(), expected);
+        }
+        let mut expected_outer = DeterministicRandom::new_with_seed(1);
+        let first: u64 = expected_outer.rng().gen_range(0..1000);
+        assert_eq!(draw()[0], first);
+    }
+
+    #[test]
+    fn sample_indices() {
+        for seed in 0..100 {
+            let mut a = DeterministicRandom::new_with_seed(seed);
+            let mut b = DeterministicRandom::new_with_seed(seed);
+
+            let indices = a.sample_indices(10, 3);
+            assert_eq!(indices.len(), 3);
+            assert!(indices.iter().all(|index| *index < 10));
+            assert_eq!(indices.iter().collect::<BTreeSet<_>>().len(), 3);
+            assert_eq!(indices, b.sample_indices(10, 3));
+
+            let mut all = a.sample_indices(5, 42);
+            assert_eq!(all.len(), 5);
+            all.sort_unstable();
+            assert_eq!(all, vec![0, 1, 2, 3, 4]);
+            assert!(a.sample_indices(0, 3).is_empty());
+        }
+    }
+
+    #[test]
+    fn flips() {
+        let mut random = DeterministicRandom::new_recording(42);
+        let flips = random.flips(100, 0.3);
+        assert_eq!(random.recorded().map(|draws| draws.len()), Some(100));
+
+        let mut expected = DeterministicRandom::new_with_seed(42);
+        let booleans: Vec<bool> = (0..100).map(|_| expected.random_boolean(0.3)).collect();
+        assert_eq!(flips, booleans);
+        assert_eq!(random.random_boolean(0.3), expected.random_boolean(0.3));
+    }
+
+    #[test]
+    fn random_duration() {
+        let range = Duration::from_millis(300)..Duration::from_secs(2);
+        let mut a = DeterministicRandom::new_with_seed(42);
+        let mut b = DeterministicRandom::new_with_seed(42);
+        let durations: Vec<Duration> = (0..1000)
+            .map(|_| a.random_duration(range.clone()))
+            .collect();
+        assert!(durations.iter().all(|duration| range.contains(duration)));
+        // not rounded to the millisecond
+        assert!(durations
+            .iter()
+            .any(|duration| duration.subsec_nanos() % 1_000_000 != 0));
+        for duration in durations {
+            assert_eq!(duration, b.random_duration(range.clone()));
+        }
+
+        let second = Duration::from_secs(1);
+        assert_eq!(a.random_duration(second..second), second);
+        // both ends are saturated, so the range is empty
+        let saturated = Duration::MAX - second..Duration::MAX;
+        assert_eq!(a.random_duration(saturated), Duration::MAX - second);
+    }
+
+    #[test]
+    fn jitter() {
+        let mut a = DeterministicRandom::new_with_seed(42);
+        let mut b = DeterministicRandom::new_with_seed(42);
+        let values: Vec<f64> = (0..1000).map(|_| a.jitter(200.0, 0.1)).collect();
+        assert!(values.iter().all(|value| (180.0..=220.0).contains(value)));
+        assert!(values.iter().any(|value| *value != 200.0));
+        for value in values {
+            assert_eq!(value, b.jitter(200.0, 0.1));
+        }
+
+        assert_eq!(a.jitter(200.0, 0.0), 200.0);
+        let negative = a.jitter(-10.0, 0.5);
+        assert!((-15.0..=-5.0).contains(&negative));
+    }
+
+    #[test]
+    fn stable() {
+        // the first outputs of the reference implementation, seeded with 42 on stream 54
+        let mut pcg = Pcg32::new(42, 54);
+        let outputs: Vec<u32> = (0..6).map(|_| pcg.next_u32()).collect();
+        assert_eq!(
+            outputs,
+            vec![0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]
+        );
+
+        let mut random = DeterministicRandom::new_stable(42);
+        let draws: Vec<u64> = (0..4).map(|_| random.rng().next_u64()).collect();
+        assert_eq!(
+            draws,
+            vec![
+                0x3c7a0d56713066ea,
+                0x25c89145f424216a,
+                0x90cff60c43e7ef3e,
+                0x53dfbcb852320591
+            ]
+        );
+        let mut other = DeterministicRandom::new_stable(42);
+        let value = other.random_between(0..1000);
+        assert_eq!(
+            value,
+            DeterministicRandom::new_stable(42).random_between(0..1000)
+        );
+    }
+
+    #[test]
+    fn reseed() {
+        for new_random in [
+            DeterministicRandom::new_with_seed,
+            DeterministicRandom::new_stable,
+        ] {
+            let mut random = new_random(42);
+            let mut shared = random.clone();
+            for _ in 0..10 {
+                random.random_between(0..1000);
+            }
+
+            // the clones are reseeded too
+            random.reseed(7);
+            let mut fresh = new_random(7);
+            for _ in 0..100 {
+                assert_eq!(
+                    shared.random_between(0..1000),
+                    fresh.random_between(0..1000)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn with_rng() {
+        let draw = |random: &mut DeterministicRandom| -> Vec<u64> {
+            (0..100)
+                .map(|_| random.random_between(0..1_000_000))
+                .collect()
+        };
+        let mut a = DeterministicRandom::with_rng::<ChaCha8Rng>(42);
+        let mut b = DeterministicRandom::with_rng::<ChaCha8Rng>(42);
+        let drawn = draw(&mut a);
+        assert_eq!(drawn, draw(&mut b));
+        assert_ne!(drawn, draw(&mut DeterministicRandom::new_with_seed(42)));
+
+        // values are the ones of the algorithm
+        let mut chacha = ChaCha8Rng::seed_from_u64(42);
+        let mut random = DeterministicRandom::with_rng::<ChaCha8Rng>(42);
+        assert_eq!(random.rng().next_u64(), chacha.next_u64());
+
+        random.reseed(7);
+        assert_eq!(
+            random.rng().next_u64(),
+            ChaCha8Rng::seed_from_u64(7).next_u64()
+        );
+    }
 }
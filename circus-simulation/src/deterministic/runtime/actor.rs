@@ -0,0 +1,144 @@
+//! Actor module
+
+use crate::deterministic::runtime::channel::SimChannel;
+use crate::deterministic::runtime::executor::Spawner;
+use crate::deterministic::runtime::reactor::DeterministicReactor;
+use crate::deterministic::runtime::task::Task;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// A reference to an actor spawned with [`spawn_actor`], used to send it messages.
+/// Each clone is a distinct sender: messages sent at the same simulated time are
+/// delivered ordered by sender. The actor's mailbox is closed once every reference is dropped.
+pub struct ActorRef<M> {
+    id: u64,
+    mailbox: Arc<Mailbox<M>>,
+}
+
+struct Mailbox<M> {
+    channel: SimChannel<M>,
+    next_sender: AtomicU64,
+}
+
+impl<M> Drop for Mailbox<M> {
+    fn drop(&mut self) {
+        self.channel.close();
+    }
+}
+
+impl<M> Clone for ActorRef<M> {
+    fn clone(&self) -> Self {
+        ActorRef {
+            id: self.mailbox.next_sender.fetch_add(1, Ordering::Relaxed),
+            mailbox: self.mailbox.clone(),
+        }
+    }
+}
+
+impl<M> ActorRef<M> {
+    /// returns the sender ID of this reference
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// sends a message to the actor. Returns the message back if the actor stopped receiving.
+    pub fn send(&self, message: M) -> Result<(), M> {
+        self.mailbox.channel.send(self.id, message)
+    }
+}
+
+/// Spawns an actor, a task consuming its mailbox. `actor` is given the mailbox, and should
+/// receive from it until it returns `None`, once every [`ActorRef`] has been dropped.
+pub fn spawn_actor<M, F, Fut>(
+    spawner: &Spawner,
+    reactor: DeterministicReactor,
+    actor: F,
+) -> ActorRef<M>
+where
+    F: FnOnce(SimChannel<M>) -> Fut,
+    Fut: Future<Output = ()> + 'static,
+{
+    let channel = SimChannel::new(reactor);
+    spawner.spawn(Task::new(actor(channel.clone())));
+    ActorRef {
+        id: 0,
+        mailbox: Arc::new(Mailbox {
+            channel,
+            next_sender: AtomicU64::new(1),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::random::DeterministicRandom;
+    use crate::deterministic::runtime::actor::{spawn_actor, ActorRef};
+    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::task::Task;
+    use crate::deterministic::runtime::timer::DeterministicTimer;
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// runs two senders sending to one actor, returns the received (send time, sender, index)
+    fn run_actor(seed: u64) -> Vec<(Duration, u64, usize)> {
+        let mut executor = DeterministicExecutor::new();
+        let reactor = executor.reactor();
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+        let received = Arc::new(RwLock::new(Vec::new()));
+
+        let log = received.clone();
+        let first: ActorRef<(Instant, u64, usize)> =
+            spawn_actor(&executor.spawner(), reactor.clone(), |mailbox| async move {
+                while let Some(message) = mailbox.recv().await {
+                    log.write().push(message);
+                }
+            });
+        let second = first.clone();
+
+        let mut random = DeterministicRandom::new_with_seed(seed);
+        // the second sender is spawned first
+        for actor in [second, first] {
+            let (time, reactor) = (time.clone(), reactor.clone());
+            let delays: Vec<u64> = (0..5).map(|_| random.random_between(0..3)).collect();
+            executor.spawn(Task::new(async move {
+                for (index, delay) in delays.into_iter().enumerate() {
+                    let delay = Duration::from_secs(delay);
+                    DeterministicTimer::wait_with_reactor(time.clone(), reactor.clone(), delay)
+                        .await;
+                    actor.send((time.now(), actor.id(), index)).unwrap();
+                }
+            }));
+        }
+        executor.run();
+
+        let received = received.read().clone();
+        received
+            .into_iter()
+            .map(|(sent_at, sender, index)| (sent_at.duration_since(start), sender, index))
+            .collect()
+    }
+
+    #[test]
+    fn test_actor() {
+        for seed in 0..10 {
+            let received = run_actor(seed);
+            assert_eq!(received.len(), 10);
+            let mut sorted = received.clone();
+            sorted.sort();
+            assert_eq!(
+                received, sorted,
+                "messages are not ordered for seed {}",
+                seed
+            );
+            assert_eq!(
+                received,
+                run_actor(seed),
+                "seed {} is not deterministic",
+                seed
+            );
+        }
+    }
+}
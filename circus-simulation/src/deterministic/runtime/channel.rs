@@ -0,0 +1,463 @@
+//! Channel module
+
+use crate::deterministic::runtime::reactor::DeterministicReactor;
+use crate::deterministic::runtime::timer::DeterministicTimer;
+use crate::deterministic::time::DeterministicTime;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// A simulated unbounded channel. Messages are received in a deterministic order:
+/// by simulated send time, then by sender ID, then in the order they were sent.
+/// To respect this order, a message is only received once the simulated instant it was sent at
+/// is over, so every message sent during that instant is known. Clones are sharing the same channel,
+/// and can receive concurrently.
+///
+/// Receiving can advance the simulated clock: when the next message was sent at the current
+/// instant, [`SimChannel::recv`] registers a 1ns wait on the reactor for the instant to end,
+/// which is visible in the time of the reactor.
+///
+/// A channel created with [`SimChannel::bounded`] holds at most `capacity` messages:
+/// [`SimChannel::send_async`] waits for a receiver to make room, and blocked senders are
+/// resumed in the order they started waiting.
+pub struct SimChannel<T> {
+    time: DeterministicTime,
+    reactor: DeterministicReactor,
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+struct Inner<T> {
+    // (sent at, sender, sequence) -> message
+    messages: BTreeMap<(Instant, u64, u64), T>,
+    sequence: u64,
+    closed: bool,
+    // receivers waiting for a message, in arrival order
+    receivers: VecDeque<(u64, Waker)>,
+    capacity: Option<usize>,
+    // identifies the blocked senders and the waiting receivers
+    next_ticket: u64,
+    // senders waiting for room, in arrival order
+    blocked: VecDeque<(u64, Waker)>,
+}
+
+impl<T> Inner<T> {
+    fn is_full(&self) -> bool {
+        self.capacity
+            .map_or(false, |capacity| self.messages.len() >= capacity)
+    }
+
+    fn push(&mut self, now: Instant, sender: u64, message: T) {
+        let sequence = self.sequence;
+        self.sequence += 1;
+        self.messages.insert((now, sender, sequence), message);
+        self.wake_receivers();
+    }
+
+    /// wakes every waiting receiver, in the order they started waiting. The receivers
+    /// not getting a message are waiting again.
+    fn wake_receivers(&mut self) {
+        for (_, waker) in self.receivers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn take_ticket(&mut self) -> u64 {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        ticket
+    }
+
+    fn pop(&mut self, key: (Instant, u64, u64)) -> Option<T> {
+        let message = self.messages.remove(&key);
+        self.wake_blocked();
+        message
+    }
+
+    /// wakes the first blocked sender, which is the only one allowed to take the room
+    fn wake_blocked(&self) {
+        if let Some((_, waker)) = self.blocked.front() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+impl<T> Clone for SimChannel<T> {
+    fn clone(&self) -> Self {
+        SimChannel {
+            time: self.time.clone(),
+            reactor: self.reactor.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> SimChannel<T> {
+    /// creates an empty `SimChannel`, timestamping messages with the reactor's time
+    pub fn new(reactor: DeterministicReactor) -> Self {
+        Self::with_capacity(reactor, None)
+    }
+
+    /// creates an empty `SimChannel` holding at most `capacity` messages
+    pub fn bounded(reactor: DeterministicReactor, capacity: usize) -> Self {
+        assert!(capacity > 0, "a bounded channel needs room for a message");
+        Self::with_capacity(reactor, Some(capacity))
+    }
+
+    fn with_capacity(reactor: DeterministicReactor, capacity: Option<usize>) -> Self {
+        SimChannel {
+            time: reactor.get_deterministic_time(),
+            reactor,
+            inner: Arc::new(Mutex::new(Inner {
+                messages: BTreeMap::new(),
+                sequence: 0,
+                closed: false,
+                receivers: VecDeque::new(),
+                capacity,
+                next_ticket: 0,
+                blocked: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// sends `message` on behalf of `sender`. Returns the message back if the channel is closed,
+    /// or if it is bounded and full.
+    pub fn send(&self, sender: u64, message: T) -> Result<(), T> {
+        let mut inner = self.inner.lock();
+        if inner.closed || inner.is_full() {
+            return Err(message);
+        }
+        inner.push(self.time.now(), sender, message);
+        Ok(())
+    }
+
+    /// sends `message` on behalf of `sender`, waiting for room if the channel is bounded and full.
+    /// Returns the message back if the channel is closed.
+    pub fn send_async(&self, sender: u64, message: T) -> SendAsync<'_, T> {
+        SendAsync {
+            channel: self,
+            sender,
+            message: Some(message),
+            ticket: None,
+        }
+    }
+
+    /// returns the next message, if there is one sent before the current simulated instant
+    pub fn try_recv(&self) -> Option<T> {
+        let mut inner = self.inner.lock();
+        let key = *inner.messages.keys().next()?;
+        if key.0 < self.time.now() {
+            inner.pop(key)
+        } else {
+            None
+        }
+    }
+
+    /// waits for the next message. Returns `None` once the channel is closed and drained.
+    /// When the next message was sent at the current instant, the simulated time is advanced
+    /// by 1ns before receiving it.
+    pub fn recv(&self) -> Recv<'_, T> {
+        Recv {
+            channel: self,
+            timer: None,
+            ticket: None,
+        }
+    }
+
+    /// closes the channel: sending is failing, and the remaining messages can still be received
+    pub fn close(&self) {
+        let mut inner = self.inner.lock();
+        inner.closed = true;
+        inner.wake_receivers();
+        for (_, waker) in inner.blocked.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// returns the number of messages waiting to be received
+    pub fn len(&self) -> usize {
+        self.inner.lock().messages.len()
+    }
+
+    /// returns true if no messages are waiting to be received
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Future returned by [`SimChannel::send_async`]
+pub struct SendAsync<'a, T> {
+    channel: &'a SimChannel<T>,
+    sender: u64,
+    message: Option<T>,
+    // position in the queue of blocked senders
+    ticket: Option<u64>,
+}
+
+impl<T> Unpin for SendAsync<'_, T> {}
+
+impl<T> Future for SendAsync<'_, T> {
+    type Output = Result<(), T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let channel = self.channel;
+        let mut inner = channel.inner.lock();
+        let message = self.message.take().expect("polled after completion");
+        if inner.closed {
+            return Poll::Ready(Err(message));
+        }
+
+        let first = match self.ticket {
+            Some(ticket) => inner.blocked.front().map(|(id, _)| *id) == Some(ticket),
+            None => inner.blocked.is_empty(),
+        };
+        if first && !inner.is_full() {
+            if self.ticket.take().is_some() {
+                inner.blocked.pop_front();
+            }
+            inner.push(channel.time.now(), self.sender, message);
+            if !inner.is_full() {
+                inner.wake_blocked();
+            }
+            return Poll::Ready(Ok(()));
+        }
+
+        self.message = Some(message);
+        match self.ticket {
+            Some(ticket) => {
+                if let Some((_, waker)) = inner.blocked.iter_mut().find(|(id, _)| *id == ticket) {
+                    *waker = cx.waker().clone();
+                }
+            }
+            None => {
+                let ticket = inner.take_ticket();
+                inner.blocked.push_back((ticket, cx.waker().clone()));
+                self.ticket = Some(ticket);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for SendAsync<'_, T> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket {
+            let mut inner = self.channel.inner.lock();
+            inner.blocked.retain(|(id, _)| *id != ticket);
+            if !inner.is_full() {
+                inner.wake_blocked();
+            }
+        }
+    }
+}
+
+/// Future returned by [`SimChannel::recv`]
+pub struct Recv<'a, T> {
+    channel: &'a SimChannel<T>,
+    // waiting for the end of the instant of the next message
+    timer: Option<DeterministicTimer>,
+    // position in the queue of waiting receivers
+    ticket: Option<u64>,
+}
+
+impl<T> Future for Recv<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            if let Some(timer) = self.timer.as_mut() {
+                match Pin::new(timer).poll(cx) {
+                    Poll::Ready(()) => self.timer = None,
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            let channel = self.channel;
+            let mut inner = channel.inner.lock();
+            match inner.messages.keys().next().copied() {
+                Some(key) if key.0 < channel.time.now() => {
+                    return Poll::Ready(inner.pop(key));
+                }
+                Some(_) => {
+                    drop(inner);
+                    // the tasks running during this instant may still send messages
+                    self.timer = Some(DeterministicTimer::wait_with_reactor(
+                        channel.time.clone(),
+                        channel.reactor.clone(),
+                        Duration::from_nanos(1),
+                    ));
+                }
+                None if inner.closed => return Poll::Ready(None),
+                None => {
+                    let ticket = match self.ticket {
+                        Some(ticket) => ticket,
+                        None => inner.take_ticket(),
+                    };
+                    match inner.receivers.iter_mut().find(|(id, _)| *id == ticket) {
+                        Some((_, waker)) => *waker = cx.waker().clone(),
+                        None => inner.receivers.push_back((ticket, cx.waker().clone())),
+                    }
+                    self.ticket = Some(ticket);
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+impl<T> Drop for Recv<'_, T> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket {
+            self.channel
+                .inner
+                .lock()
+                .receivers
+                .retain(|(id, _)| *id != ticket);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::runtime::channel::SimChannel;
+    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::task::Task;
+    use crate::deterministic::runtime::timer::DeterministicTimer;
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_channel() {
+        let mut executor = DeterministicExecutor::new();
+        let channel = SimChannel::new(executor.reactor());
+        let received = Arc::new(RwLock::new(Vec::new()));
+
+        let receiver = channel.clone();
+        let log = received.clone();
+        executor.spawn(Task::new(async move {
+            while let Some(message) = receiver.recv().await {
+                log.write().push(message);
+            }
+        }));
+
+        // same send time, ordered by sender
+        channel.send(2, "second").unwrap();
+        channel.send(1, "first").unwrap();
+        channel.send(2, "third").unwrap();
+        assert_eq!(channel.len(), 3);
+        assert_eq!(channel.try_recv(), None, "the instant is not over");
+        channel.close();
+        assert_eq!(channel.send(1, "closed"), Err("closed"));
+
+        executor.run();
+        assert_eq!(*received.read(), vec!["first", "second", "third"]);
+        assert!(channel.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_receivers() {
+        let mut executor = DeterministicExecutor::new();
+        let reactor = executor.reactor();
+        let channel = SimChannel::new(reactor.clone());
+        let received = Arc::new(RwLock::new(Vec::new()));
+
+        // both receivers are waiting on an empty channel, for a message each
+        for receiver in ["first", "second"] {
+            let (channel, log) = (channel.clone(), received.clone());
+            executor.spawn(Task::new(async move {
+                let message = channel.recv().await.expect("a message should be received");
+                log.write().push((receiver, message));
+            }));
+        }
+
+        let sender = channel.clone();
+        let time = reactor.get_deterministic_time();
+        executor.spawn(Task::new(async move {
+            for message in 0..2 {
+                DeterministicTimer::wait_with_reactor(
+                    time.clone(),
+                    reactor.clone(),
+                    Duration::from_secs(1),
+                )
+                .await;
+                sender.send(1, message).unwrap();
+            }
+            sender.close();
+        }));
+
+        // both receivers are woken, none of them is left waiting
+        executor.run();
+        assert_eq!(*received.read(), vec![("first", 0), ("second", 1)]);
+    }
+
+    /// two fast senders and a slow receiver on a capacity-1 channel
+    fn run_backpressure() -> Vec<(String, u64)> {
+        let mut executor = DeterministicExecutor::new();
+        let reactor = executor.reactor();
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+        let channel = SimChannel::bounded(reactor.clone(), 1);
+        let log = Arc::new(RwLock::new(Vec::new()));
+
+        for sender in [1, 2] {
+            let (channel, log, time) = (channel.clone(), log.clone(), time.clone());
+            executor.spawn(Task::new(async move {
+                for i in 0..2 {
+                    channel.send_async(sender, (sender, i)).await.unwrap();
+                    let elapsed = time.now().duration_since(start).as_secs();
+                    log.write()
+                        .push((format!("sent {}:{}", sender, i), elapsed));
+                }
+            }));
+        }
+
+        let receiver = channel.clone();
+        let history = log.clone();
+        executor.spawn(Task::new(async move {
+            for _ in 0..4 {
+                DeterministicTimer::wait_with_reactor(
+                    time.clone(),
+                    reactor.clone(),
+                    Duration::from_secs(1),
+                )
+                .await;
+                let (sender, i) = receiver.recv().await.unwrap();
+                let elapsed = time.now().duration_since(start).as_secs();
+                history
+                    .write()
+                    .push((format!("received {}:{}", sender, i), elapsed));
+            }
+        }));
+
+        executor.run();
+        let log = log.read().clone();
+        log
+    }
+
+    #[test]
+    fn test_bounded_channel() {
+        let log = run_backpressure();
+        let expected = [
+            ("sent 1:0", 0),
+            // both senders are blocked until the receiver makes room, and are resumed
+            // in the order they started waiting
+            ("received 1:0", 1),
+            ("sent 1:1", 1),
+            ("received 1:1", 2),
+            ("sent 2:0", 2),
+            ("received 2:0", 3),
+            ("sent 2:1", 3),
+            ("received 2:1", 4),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(event, at)| (event.to_string(), *at))
+            .collect();
+        assert_eq!(log, expected);
+        assert_eq!(log, run_backpressure());
+    }
+}
@@ -0,0 +1,143 @@
+//! Event module
+
+use parking_lot::Mutex;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+/// A simulated event, used by a task to signal others without going through a timer.
+/// Futures returned by [`SimEvent::wait`] stay pending until [`SimEvent::notify`] is called,
+/// and waiters are woken in the order they started waiting. Clones are sharing the same event.
+#[derive(Clone, Default)]
+pub struct SimEvent {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    // number of notifications so far
+    generation: u64,
+    next_waiter: u64,
+    waiters: Vec<(u64, Waker)>,
+}
+
+impl SimEvent {
+    /// creates a new `SimEvent`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// returns a future completing on the next notification
+    pub fn wait(&self) -> Wait {
+        let mut inner = self.inner.lock();
+        let id = inner.next_waiter;
+        inner.next_waiter += 1;
+        Wait {
+            inner: self.inner.clone(),
+            generation: inner.generation,
+            id,
+        }
+    }
+
+    /// wakes every waiter, in the order they started waiting
+    pub fn notify(&self) {
+        let waiters = {
+            let mut inner = self.inner.lock();
+            inner.generation += 1;
+            std::mem::take(&mut inner.waiters)
+        };
+        tracing::trace!("notifying {} waiters", waiters.len());
+        for (_, waker) in waiters {
+            waker.wake();
+        }
+    }
+}
+
+/// Future returned by [`SimEvent::wait`]
+pub struct Wait {
+    inner: Arc<Mutex<Inner>>,
+    generation: u64,
+    id: u64,
+}
+
+impl Future for Wait {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut inner = self.inner.lock();
+        if inner.generation > self.generation {
+            return Poll::Ready(());
+        }
+        match inner.waiters.iter_mut().find(|(id, _)| *id == self.id) {
+            Some((_, waker)) => *waker = cx.waker().clone(),
+            None => inner.waiters.push((self.id, cx.waker().clone())),
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Wait {
+    fn drop(&mut self) {
+        let id = self.id;
+        self.inner
+            .lock()
+            .waiters
+            .retain(|(waiter, _)| *waiter != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::runtime::event::SimEvent;
+    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::task::Task;
+    use crate::deterministic::runtime::timer::DeterministicTimer;
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_event() {
+        let mut executor = DeterministicExecutor::new();
+        let reactor = executor.reactor();
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+        let event = SimEvent::new();
+        let log = Arc::new(RwLock::new(Vec::new()));
+
+        for name in ["first", "second", "third"] {
+            let (event, log, time) = (event.clone(), log.clone(), time.clone());
+            executor.spawn(Task::new(async move {
+                event.wait().await;
+                log.write().push((name, time.now().duration_since(start)));
+            }));
+        }
+
+        let notifier = event.clone();
+        executor.spawn(Task::new(async move {
+            DeterministicTimer::wait_with_reactor(time, reactor, Duration::from_secs(1)).await;
+            notifier.notify();
+        }));
+        executor.run();
+
+        let woken_at = Duration::from_secs(1);
+        assert_eq!(
+            *log.read(),
+            vec![
+                ("first", woken_at),
+                ("second", woken_at),
+                ("third", woken_at)
+            ]
+        );
+
+        // a wait started after a notification needs another one
+        let late = event.clone();
+        executor.spawn(Task::new(async move {
+            late.wait().await;
+        }));
+        assert!(executor.try_run().is_err());
+        event.notify();
+        executor.run();
+    }
+}
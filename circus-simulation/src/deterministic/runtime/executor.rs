@@ -1,129 +1,710 @@
 //! Executor module
 
+use crate::deterministic::random::DeterministicRandom;
 use crate::deterministic::runtime::reactor::DeterministicReactor;
 use crate::deterministic::runtime::task::{Task, TaskId};
-use crossbeam_queue::ArrayQueue;
-use std::collections::BTreeMap;
-use std::sync::Arc;
+use crate::deterministic::runtime::timer::DeterministicTimer;
+use crate::error::SimError;
+use circus_buggify::BuggifyMode;
+use parking_lot::Mutex;
+use rand::seq::SliceRandom;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Weak};
 use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// A deterministic, single-threaded executor that can be used in simulation mode.
 /// Combined with the [`DeterministicReactor`], this is allowing developers to pull and schedule
 /// futures in a deterministic way.
 /// This has been developed by reading [this blogpost](https://os.phil-opp.com/async-await/#executor-with-waker-support).
+///
+/// ## Scheduling policy
+/// Tasks are scheduled in rounds. At the start of a round, the executor takes a snapshot of the
+/// tasks that are ready, and polls each of them exactly once, in the order they were woken (FIFO).
+/// A task woken during a round, including a task waking itself, is only polled on the next round,
+/// so a busy task cannot starve the others. A task woken several times is polled once per round,
+/// at the position of its first wake. Task IDs never decide the order: a task with a higher ID
+/// woken first is polled first. Tasks do not have priorities: a task that is ready
+/// is polled within the next round, even if other tasks keep spawning new tasks.
+///
+/// To explore more interleavings, the ready tasks of each round can instead be polled in an
+/// order shuffled by a seed, see [`ExecutorBuilder::buggify_order`].
+///
+/// ## Capacity
+/// By default, any number of tasks can be spawned. The number of tasks can be bounded with
+/// a [`QueuePolicy`], see [`DeterministicExecutor::builder`].
+///
+/// ## Threads
+/// The executor and its tasks live on a single thread, so the futures do not need to be `Send`.
+/// Only the wakers are `Send` and `Sync`, as required by [`Waker`]: waking a task is pushing
+/// its ID on a thread-safe queue, without touching the future.
+///
+/// ## Observers
+/// The lifecycle of the tasks and of the simulated time can be followed by registering
+/// an [`ExecutorObserver`] with [`DeterministicExecutor::observe`]. A long simulation can
+/// also report its [`Progress`] periodically, see [`DeterministicExecutor::report_progress`].
 pub struct DeterministicExecutor {
-    tasks: BTreeMap<TaskId, Task>,
-    task_queue: Arc<ArrayQueue<TaskId>>,
+    pub(crate) tasks: BTreeMap<TaskId, Task>,
+    task_queue: Arc<TaskQueue>,
     waker_cache: BTreeMap<TaskId, Waker>,
     reactor: DeterministicReactor,
+    spawner: Spawner,
+    pacing: Option<f64>,
+    // the ID of the next spawned task, IDs are not shared between executors
+    next_task_id: u64,
+    observers: Vec<Box<dyn ExecutorObserver>>,
+    // shuffles the ready tasks of each round, when set
+    order: Option<DeterministicRandom>,
+    // number of polls of each task, kept after its completion
+    poll_counts: BTreeMap<TaskId, u64>,
+    // a warning is logged once a task is polled more than this
+    hot_loop_threshold: Option<u64>,
+    // called every N advances of the simulation
+    progress: Option<(u64, ProgressCallback)>,
 }
 
-impl Default for DeterministicExecutor {
+/// callback registered with [`DeterministicExecutor::report_progress`]
+type ProgressCallback = Box<dyn FnMut(Progress)>;
+
+/// Summary of a [`DeterministicExecutor::run`], comparing the simulated time with the real time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunReport {
+    /// simulated time advanced during the run
+    pub simulated: Duration,
+    /// real time spent during the run
+    pub real: Duration,
+}
+
+impl RunReport {
+    /// returns how many times faster than real time the simulation ran
+    pub fn speedup(&self) -> f64 {
+        self.simulated.as_secs_f64() / self.real.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "simulated {:?} in {:?}", self.simulated, self.real)
+    }
+}
+
+/// Progress of a run, given to the callback registered with
+/// [`DeterministicExecutor::report_progress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// number of times the simulated time advanced since the start of the run
+    pub advances: u64,
+    /// simulated time advanced since the start of the run
+    pub simulated: Duration,
+    /// number of tasks still registered on the executor
+    pub tasks: usize,
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "simulated {:?} in {} advances, {} tasks remaining",
+            self.simulated, self.advances, self.tasks
+        )
+    }
+}
+
+/// Callbacks called by a [`DeterministicExecutor`] on each lifecycle event, in the order they
+/// happen. Useful to build visualizations, or to check invariants on the scheduling.
+/// Every callback does nothing by default.
+pub trait ExecutorObserver {
+    /// a task was registered on the executor
+    fn on_spawn(&mut self, _id: TaskId) {}
+    /// a task is about to be polled
+    fn on_poll(&mut self, _id: TaskId) {}
+    /// a task completed, and was removed from the executor
+    fn on_complete(&mut self, _id: TaskId) {}
+    /// the simulated time advanced
+    fn on_advance(&mut self, _from: Instant, _to: Instant) {}
+}
+
+/// How many tasks a [`DeterministicExecutor`] accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// any number of tasks
+    Unbounded,
+    /// at most `capacity` tasks, `overflow` tells what happens to the tasks spawned past it
+    Bounded {
+        /// maximum number of tasks
+        capacity: usize,
+        /// what to do with a task spawned past the capacity
+        overflow: Overflow,
+    },
+}
+
+impl Default for QueuePolicy {
     fn default() -> Self {
-        Self::new()
+        QueuePolicy::Unbounded
     }
 }
 
-impl DeterministicExecutor {
-    /// create a new deterministic executor
-    pub fn new_with_reactor(reactor: DeterministicReactor) -> Self {
+/// What a bounded [`DeterministicExecutor`] does with a task spawned past its capacity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// [`Spawner::spawn_async`] waits for room. Only the async path applies backpressure:
+    /// tasks spawned synchronously cannot wait, so they are accepted past the capacity,
+    /// with a warning
+    Backpressure,
+    /// spawning panics
+    Panic,
+    /// the task is dropped without being run
+    Drop,
+}
+
+/// Builder for a [`DeterministicExecutor`]
+#[derive(Default)]
+pub struct ExecutorBuilder {
+    reactor: Option<DeterministicReactor>,
+    queue: QueuePolicy,
+    order: Option<DeterministicRandom>,
+    hot_loop_threshold: Option<u64>,
+}
+
+impl ExecutorBuilder {
+    /// advances the given reactor, instead of a reactor of its own
+    pub fn reactor(mut self, reactor: DeterministicReactor) -> Self {
+        self.reactor = Some(reactor);
+        self
+    }
+
+    /// sets how many tasks the executor accepts, [`QueuePolicy::Unbounded`] by default
+    pub fn queue(mut self, policy: QueuePolicy) -> Self {
+        self.queue = policy;
+        self
+    }
+
+    /// polls the ready tasks of each round in an order shuffled by `seed`, instead of the order
+    /// they were woken. The order is still reproducible for a given seed.
+    pub fn buggify_order(mut self, seed: u64) -> Self {
+        self.order = Some(DeterministicRandom::new_with_seed(seed));
+        self
+    }
+
+    /// in [`BuggifyMode::Chaos`], polls the ready tasks in an order shuffled by `seed`,
+    /// see [`ExecutorBuilder::buggify_order`]. The normal mode keeps the wake order.
+    pub fn buggify_mode(self, mode: BuggifyMode, seed: u64) -> Self {
+        if mode.is_chaos() {
+            self.buggify_order(seed)
+        } else {
+            self
+        }
+    }
+
+    /// logs a warning when a task is polled more than `threshold` times, which usually means
+    /// it is busy-waiting by waking itself. See [`DeterministicExecutor::poll_count`].
+    pub fn warn_polls_above(mut self, threshold: u64) -> Self {
+        self.hot_loop_threshold = Some(threshold);
+        self
+    }
+
+    /// builds the executor
+    pub fn build(self) -> DeterministicExecutor {
+        let reactor = self.reactor.unwrap_or_default();
+        let spawner = Spawner::default();
+        spawner.inner.borrow_mut().policy = self.queue;
+        spawner.inner.borrow_mut().reactor = reactor.clone();
         DeterministicExecutor {
             tasks: BTreeMap::new(),
-            task_queue: Arc::new(ArrayQueue::new(100)),
+            task_queue: Arc::new(TaskQueue::default()),
             waker_cache: BTreeMap::new(),
             reactor,
+            spawner,
+            pacing: None,
+            next_task_id: 0,
+            observers: Vec::new(),
+            order: self.order,
+            poll_counts: BTreeMap::new(),
+            hot_loop_threshold: self.hot_loop_threshold,
+            progress: None,
+        }
+    }
+}
+
+/// A cloneable handle used to spawn tasks on a [`DeterministicExecutor`]. It can be captured
+/// by a future to spawn new tasks while the executor is running. Spawned tasks are picked up
+/// by the executor at the end of the current round, in the order they were spawned, and are
+/// polled on the next round after the tasks woken during the current one.
+#[derive(Clone, Default)]
+pub struct Spawner {
+    inner: Rc<RefCell<SpawnerInner>>,
+}
+
+#[derive(Default)]
+struct SpawnerInner {
+    pending: VecDeque<Task>,
+    policy: QueuePolicy,
+    // number of tasks registered on the executor
+    running: usize,
+    // wakers of the `SpawnAsync` waiting for room
+    waiting: Vec<Waker>,
+    // the reactor of the executor, used by `Spawner::spawn_after`
+    reactor: DeterministicReactor,
+}
+
+impl SpawnerInner {
+    fn has_room(&self) -> bool {
+        match self.policy {
+            QueuePolicy::Unbounded => true,
+            QueuePolicy::Bounded { capacity, .. } => self.running + self.pending.len() < capacity,
+        }
+    }
+}
+
+impl Spawner {
+    /// register a task, according to the [`QueuePolicy`] of the executor. It does not wait
+    /// for room under [`Overflow::Backpressure`], use [`Spawner::spawn_async`] for that
+    pub fn spawn(&self, task: Task) {
+        tracing::trace!("spawning a task");
+        self.inner.borrow_mut().pending.push_back(task);
+    }
+
+    /// register a task starting `future` once `delay` of simulated time elapsed from now
+    pub fn spawn_after(&self, delay: Duration, future: impl Future<Output = ()> + 'static) {
+        let reactor = self.inner.borrow().reactor.clone();
+        self.spawn(Task::new(delayed(reactor, delay, future)));
+    }
+
+    /// register a task once the executor has room for it, see [`Overflow::Backpressure`]
+    pub fn spawn_async(&self, task: Task) -> SpawnAsync {
+        SpawnAsync {
+            spawner: self.clone(),
+            task: Some(task),
         }
     }
-    /// creates a new Executor with a global Reactor
+
+    fn take(&self) -> Option<Task> {
+        self.inner.borrow_mut().pending.pop_front()
+    }
+
+    fn policy(&self) -> QueuePolicy {
+        self.inner.borrow().policy
+    }
+
+    /// updates the number of registered tasks, waking the spawns waiting for room
+    fn set_running(&self, running: usize) {
+        let waiting = {
+            let mut inner = self.inner.borrow_mut();
+            inner.running = running;
+            if !inner.has_room() {
+                return;
+            }
+            std::mem::take(&mut inner.waiting)
+        };
+        for waker in waiting {
+            waker.wake();
+        }
+    }
+}
+
+/// returns `duration * factor`, saturating at [`Duration::MAX`] instead of panicking
+fn paced(duration: Duration, factor: f64) -> Duration {
+    let seconds = duration.as_secs_f64() * factor;
+    if seconds >= Duration::MAX.as_secs_f64() {
+        Duration::MAX
+    } else {
+        Duration::from_secs_f64(seconds)
+    }
+}
+
+/// waits for `delay` before running `future`, the delay starting now
+fn delayed(
+    reactor: DeterministicReactor,
+    delay: Duration,
+    future: impl Future<Output = ()> + 'static,
+) -> impl Future<Output = ()> + 'static {
+    let timer =
+        DeterministicTimer::wait_with_reactor(reactor.get_deterministic_time(), reactor, delay);
+    async move {
+        timer.await;
+        future.await;
+    }
+}
+
+/// Future returned by [`Spawner::spawn_async`]
+pub struct SpawnAsync {
+    spawner: Spawner,
+    task: Option<Task>,
+}
+
+impl Future for SpawnAsync {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let spawner = self.spawner.clone();
+        let mut inner = spawner.inner.borrow_mut();
+        if !inner.has_room() {
+            inner.waiting.push(cx.waker().clone());
+            return Poll::Pending;
+        }
+        if let Some(task) = self.task.take() {
+            tracing::trace!("spawning a task");
+            inner.pending.push_back(task);
+        }
+        Poll::Ready(())
+    }
+}
+
+impl Default for DeterministicExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeterministicExecutor {
+    /// create a new deterministic executor
+    pub fn new_with_reactor(reactor: DeterministicReactor) -> Self {
+        Self::builder().reactor(reactor).build()
+    }
+    /// creates a new Executor with its own Reactor
     pub fn new() -> Self {
-        DeterministicExecutor {
-            tasks: BTreeMap::new(),
-            task_queue: Arc::new(ArrayQueue::new(100)),
-            waker_cache: BTreeMap::new(),
-            reactor: DeterministicReactor::default(),
+        Self::builder().build()
+    }
+
+    /// returns a builder to configure an executor
+    pub fn builder() -> ExecutorBuilder {
+        ExecutorBuilder::default()
+    }
+
+    /// paces the simulation with the real time: each time the simulation advances by a duration,
+    /// the executor is sleeping for `duration * factor` of real time. Useful to make a demo watchable.
+    /// By default, the simulation advances instantly. A negative or NaN factor is ignored,
+    /// with a warning, and a single pause is capped at [`Duration::MAX`].
+    pub fn pace(&mut self, factor: f64) {
+        if factor.is_nan() || factor < 0.0 {
+            tracing::warn!("ignoring the invalid pacing factor {}", factor);
+            self.pacing = None;
+            return;
+        }
+        self.pacing = Some(factor);
+    }
+
+    /// registers an observer, called on each lifecycle event after the observers registered before
+    pub fn observe(&mut self, observer: impl ExecutorObserver + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// calls `callback` every `every` advances of the simulated time, so a long simulation can
+    /// show that it is still making progress. The callback is only given a copy of the
+    /// [`Progress`] and cannot reach the executor, so reporting does not change the simulation.
+    /// Replaces the callback registered before, if any.
+    pub fn report_progress(&mut self, every: u64, callback: impl FnMut(Progress) + 'static) {
+        self.progress = Some((every.max(1), Box::new(callback)));
+    }
+
+    /// returns the IDs of the tasks registered on the executor, sorted
+    pub fn task_ids(&self) -> Vec<TaskId> {
+        self.tasks.keys().copied().collect()
+    }
+
+    /// returns how many times the task `id` was polled, including after its completion
+    pub fn poll_count(&self, id: TaskId) -> u64 {
+        self.poll_counts.get(&id).copied().unwrap_or(0)
+    }
+
+    /// returns a human-readable description of the registered tasks, of the tasks woken
+    /// and waiting to be polled, and of the waits pending on the reactor.
+    /// Useful to find out what is still alive when a simulation stalls.
+    pub fn dump_state(&self) -> String {
+        let queued = self.task_queue.snapshot();
+        let waits = self.reactor.pending_remaining();
+
+        let list = |items: Vec<String>| items.join(", ");
+        format!(
+            "tasks ({}): {}\nqueue ({}): {}\nreactor waits ({}): {}\n",
+            self.tasks.len(),
+            list(self.tasks.keys().map(|id| format!("{:?}", id)).collect()),
+            queued.len(),
+            list(queued.iter().map(|id| format!("{:?}", id)).collect()),
+            waits.len(),
+            list(waits.iter().map(|wait| format!("in {:?}", wait)).collect()),
+        )
+    }
+
+    /// returns the reactor advanced by this executor
+    pub fn reactor(&self) -> DeterministicReactor {
+        self.reactor.clone()
+    }
+
+    /// main blocking loop, that will poll every registered futures.
+    /// Returns the simulated time advanced and the real time spent during the run.
+    ///
+    /// # Panics
+    /// Panics if the simulation is deadlocked, see [`DeterministicExecutor::try_run`].
+    pub fn run(&mut self) -> RunReport {
+        let time = self.reactor.get_deterministic_time();
+        let (start, real_start) = (time.now(), Instant::now());
+        if let Err(error) = self.try_run() {
+            panic!("{}", error);
         }
+        let report = RunReport {
+            simulated: time.now().saturating_duration_since(start),
+            real: real_start.elapsed(),
+        };
+        tracing::debug!("{}", report);
+        report
     }
 
     /// main blocking loop, that will poll every registered futures.
-    pub fn run(&mut self) {
+    /// Returns [`SimError::Deadlock`] if some tasks are still pending but the simulation
+    /// cannot advance anymore.
+    pub fn try_run(&mut self) -> Result<(), SimError> {
+        self.run_until(false)
+    }
+
+    /// Runs until every task completed and the reactor has no pending waits left, advancing
+    /// the simulation through the waits that outlived their task.
+    /// Returns the simulated time elapsed, or [`SimError::Deadlock`] if some tasks are still
+    /// pending but the simulation cannot advance anymore.
+    pub fn run_to_quiescence(&mut self) -> Result<Duration, SimError> {
+        let time = self.reactor.get_deterministic_time();
+        let start = time.now();
+        self.run_until(true)?;
+        Ok(time.now().duration_since(start))
+    }
+
+    /// main loop, stops once every task is done, and if `quiescence` is set,
+    /// when the reactor has nothing left to wait for
+    fn run_until(&mut self, quiescence: bool) -> Result<(), SimError> {
+        let start = self.reactor.get_deterministic_time().now();
+        let mut advances = 0;
         loop {
             self.run_ready_tasks();
 
-            if self.waker_cache.is_empty() && self.task_queue.is_empty() && self.tasks.is_empty() {
-                break;
+            if self.waker_cache.is_empty()
+                && self.task_queue.is_empty()
+                && self.tasks.is_empty()
+                && (!quiescence || self.reactor.pending_waits() == 0)
+            {
+                return Ok(());
             }
 
             if self.task_queue.is_empty() {
                 // we have nothing to do here, we can advance simulation
+                let time = self.reactor.get_deterministic_time();
+                let from = time.now();
                 match self.reactor.advance_simulation() {
-                    None => unreachable!("simulation should always be able to advance"),
-                    Some(duration) => tracing::trace!("advanced simulation for {:?}", duration),
+                    None => {
+                        tracing::warn!("deadlock detected with {} tasks pending", self.tasks.len());
+                        return Err(SimError::Deadlock);
+                    }
+                    Some(duration) => {
+                        tracing::trace!("advanced simulation for {:?}", duration);
+                        let to = time.now();
+                        for observer in self.observers.iter_mut() {
+                            observer.on_advance(from, to);
+                        }
+                        advances += 1;
+                        if let Some((every, callback)) = self.progress.as_mut() {
+                            if advances % *every == 0 {
+                                callback(Progress {
+                                    advances,
+                                    simulated: to.duration_since(start),
+                                    tasks: self.tasks.len(),
+                                });
+                            }
+                        }
+                        if let Some(factor) = self.pacing {
+                            thread::sleep(paced(duration, factor));
+                        }
+                    }
                 }
             }
-
-            // useful to debug
-            // thread::sleep(Duration::from_secs(1));
         }
     }
 
-    /// register a task
+    /// register a task, according to the [`QueuePolicy`] of the executor. It does not wait
+    /// for room under [`Overflow::Backpressure`], use [`Spawner::spawn_async`] for that
     pub fn spawn(&mut self, task: Task) {
-        tracing::trace!("adding task {:?}", task.id);
-        let task_id = task.id;
-        if self.tasks.insert(task.id, task).is_some() {
-            panic!("task with same ID already in tasks");
+        let task_id = TaskId::new(self.next_task_id);
+        self.next_task_id += 1;
+
+        if let QueuePolicy::Bounded { capacity, overflow } = self.spawner.policy() {
+            if self.tasks.len() >= capacity {
+                match overflow {
+                    Overflow::Panic => panic!("task queue is full, capacity is {}", capacity),
+                    Overflow::Drop => {
+                        tracing::warn!("task queue is full, dropping task {:?}", task_id);
+                        return;
+                    }
+                    Overflow::Backpressure => {
+                        tracing::warn!("task queue is full, accepting task {:?}", task_id)
+                    }
+                }
+            }
+        }
+
+        tracing::trace!("adding task {:?}", task_id);
+        let previous = self.tasks.insert(task_id, task);
+        debug_assert!(previous.is_none(), "task with same ID already in tasks");
+        for observer in self.observers.iter_mut() {
+            observer.on_spawn(task_id);
+        }
+        self.task_queue.push(task_id);
+        self.spawner.set_running(self.tasks.len());
+    }
+
+    /// register a task starting `future` once `delay` of simulated time elapsed from now,
+    /// according to the [`QueuePolicy`] of the executor
+    pub fn spawn_after(&mut self, delay: Duration, future: impl Future<Output = ()> + 'static) {
+        let reactor = self.reactor.clone();
+        self.spawn(Task::new(delayed(reactor, delay, future)));
+    }
+
+    /// returns a [`Spawner`] that can be used to spawn tasks from within a running task
+    pub fn spawner(&self) -> Spawner {
+        self.spawner.clone()
+    }
+
+    /// register the tasks spawned through the [`Spawner`]
+    fn register_spawned_tasks(&mut self) {
+        while let Some(task) = self.spawner.take() {
+            self.spawn(task);
         }
-        self.task_queue.push(task_id).expect("queue full");
     }
 
-    fn run_ready_tasks(&mut self) {
+    /// run a single round, polling every ready task once
+    pub(crate) fn run_ready_tasks(&mut self) {
+        self.register_spawned_tasks();
+
         // destructure `self` to avoid borrow checker errors
         let Self {
             tasks,
             task_queue,
             waker_cache,
             reactor: _,
+            spawner,
+            pacing: _,
+            next_task_id: _,
+            observers,
+            order,
+            poll_counts,
+            hot_loop_threshold,
+            progress: _,
         } = self;
 
-        while let Some(task_id) = task_queue.pop() {
+        // only poll the tasks that were ready when the round started,
+        // tasks woken during this round will be polled on the next one.
+        // A task woken several times is polled once, at its first wake
+        let mut woken = BTreeSet::new();
+        let mut ready: Vec<TaskId> = task_queue
+            .take()
+            .into_iter()
+            .filter(|task_id| woken.insert(*task_id))
+            .collect();
+        if let Some(random) = order {
+            ready.shuffle(&mut random.rng());
+        }
+        for task_id in ready {
             let task = match tasks.get_mut(&task_id) {
                 Some(task) => task,
-                None => continue, // task no longer exists
+                None => {
+                    // the task is done, but its waker was kept and used
+                    tracing::warn!("task {:?} woken after its completion", task_id);
+                    continue;
+                }
+            };
+            // the waker is only cached once the task is pending, so a task completing on its
+            // first poll never has an entry in the cache
+            let waker = match waker_cache.get(&task_id) {
+                Some(waker) => waker.clone(),
+                None => TaskWaker::new_waker(task_id, task_queue.clone()),
             };
-            let waker = waker_cache
-                .entry(task_id)
-                .or_insert_with(|| TaskWaker::new_waker(task_id, task_queue.clone()));
-            let mut context = Context::from_waker(waker);
+            for observer in observers.iter_mut() {
+                observer.on_poll(task_id);
+            }
+            let polls = poll_counts.entry(task_id).or_insert(0);
+            *polls += 1;
+            if Some(*polls) == hot_loop_threshold.map(|threshold| threshold.saturating_add(1)) {
+                tracing::warn!(
+                    "task {:?} was polled more than {} times, is it busy-waiting?",
+                    task_id,
+                    *polls - 1
+                );
+            }
+            let mut context = Context::from_waker(&waker);
             match task.poll(&mut context) {
                 Poll::Ready(()) => {
                     tracing::trace!("removing task {:?}", task_id);
                     // task done -> remove it and its cached waker
                     tasks.remove(&task_id);
                     waker_cache.remove(&task_id);
+                    spawner.set_running(tasks.len());
+                    for observer in observers.iter_mut() {
+                        observer.on_complete(task_id);
+                    }
+                }
+                Poll::Pending => {
+                    waker_cache.entry(task_id).or_insert(waker);
                 }
-                Poll::Pending => {}
             }
         }
+
+        self.register_spawned_tasks();
+    }
+}
+
+/// The IDs of the woken tasks waiting to be polled, in the order they were woken.
+/// Unlike a lock-free queue, it can be looked at without being drained.
+#[derive(Debug, Default)]
+pub struct TaskQueue(Mutex<VecDeque<TaskId>>);
+
+impl TaskQueue {
+    fn push(&self, task_id: TaskId) {
+        self.0.lock().push_back(task_id);
+    }
+
+    /// removes every queued ID
+    fn take(&self) -> VecDeque<TaskId> {
+        std::mem::take(&mut *self.0.lock())
+    }
+
+    fn snapshot(&self) -> Vec<TaskId> {
+        self.0.lock().iter().copied().collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.lock().is_empty()
     }
 }
 
-/// TaskWaker implements `Waker`
+/// TaskWaker implements `Waker`. It does not keep the executor's queue alive,
+/// so a waker used after the executor has been dropped is ignored.
 pub struct TaskWaker {
     task_id: TaskId,
-    task_queue: Arc<ArrayQueue<TaskId>>,
+    task_queue: Weak<TaskQueue>,
 }
 
 impl TaskWaker {
     /// create a new TaskWaker
-    pub fn new_waker(task_id: TaskId, task_queue: Arc<ArrayQueue<TaskId>>) -> Waker {
+    pub fn new_waker(task_id: TaskId, task_queue: Arc<TaskQueue>) -> Waker {
         Waker::from(Arc::new(TaskWaker {
             task_id,
-            task_queue,
+            task_queue: Arc::downgrade(&task_queue),
         }))
     }
     fn wake_task(&self) {
-        tracing::trace!("waking task {:?}", self.task_id);
-        self.task_queue.push(self.task_id).expect("task_queue full");
+        match self.task_queue.upgrade() {
+            Some(task_queue) => {
+                tracing::trace!("waking task {:?}", self.task_id);
+                task_queue.push(self.task_id);
+            }
+            None => tracing::warn!(
+                "task {:?} woken after its executor has been dropped",
+                self.task_id
+            ),
+        }
     }
 }
 
@@ -139,13 +720,23 @@ impl Wake for TaskWaker {
 
 #[cfg(test)]
 mod tests {
-    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::executor::{
+        paced, DeterministicExecutor, ExecutorObserver, Overflow, Progress, QueuePolicy, Spawner,
+    };
     use crate::deterministic::runtime::reactor::DeterministicReactor;
-    use crate::deterministic::runtime::task::Task;
+    use crate::deterministic::runtime::task::{Task, TaskId};
     use crate::deterministic::runtime::timer::DeterministicTimer;
     use crate::deterministic::time::DeterministicTime;
+    use crate::error::SimError;
+    use circus_buggify::BuggifyMode;
     use parking_lot::RwLock;
+    use std::cell::RefCell;
+    use std::collections::BTreeSet;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::rc::Rc;
     use std::sync::Arc;
+    use std::task::{Context, Poll, Waker};
     use std::time::{Duration, Instant};
     use tracing::Level;
 
@@ -212,4 +803,748 @@ mod tests {
             );
         }
     }
+
+    /// A future that wakes itself and returns `Pending` a fixed number of times.
+    struct YieldNow {
+        remaining: usize,
+    }
+
+    impl Future for YieldNow {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.remaining == 0 {
+                return Poll::Ready(());
+            }
+            self.remaining -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    async fn yielding_task(name: &'static str, rounds: usize, log: Arc<RwLock<Vec<&'static str>>>) {
+        for _ in 0..rounds {
+            log.write().push(name);
+            YieldNow { remaining: 1 }.await;
+        }
+    }
+
+    /// A future waking itself twice, then returning `Pending`, a fixed number of times.
+    struct DoubleWake {
+        remaining: usize,
+        polls: Arc<RwLock<usize>>,
+    }
+
+    impl Future for DoubleWake {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            *self.polls.write() += 1;
+            if self.remaining == 0 {
+                return Poll::Ready(());
+            }
+            self.remaining -= 1;
+            cx.waker().wake_by_ref();
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_double_wake() {
+        let mut executor = DeterministicExecutor::new();
+        let polls = Arc::new(RwLock::new(0));
+        executor.spawn(Task::new(DoubleWake {
+            remaining: 3,
+            polls: polls.clone(),
+        }));
+
+        for round in 1..=4 {
+            executor.run_ready_tasks();
+            assert_eq!(*polls.read(), round, "task polled more than once per round");
+        }
+        assert!(executor.tasks.is_empty());
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut executor = DeterministicExecutor::new();
+        let log = Arc::new(RwLock::new(Vec::new()));
+
+        // the busy task keeps re-queuing itself, the other one must still progress every round
+        executor.spawn(Task::new(yielding_task("busy", 100, log.clone())));
+        executor.spawn(Task::new(yielding_task("other", 3, log.clone())));
+
+        for round in 0..3 {
+            executor.run_ready_tasks();
+            assert_eq!(
+                log.read().len(),
+                (round + 1) * 2,
+                "each task should be polled once per round"
+            );
+            assert_eq!(&log.read()[round * 2..], &["busy", "other"]);
+        }
+
+        executor.run();
+        assert_eq!(
+            log.read().iter().filter(|name| **name == "other").count(),
+            3
+        );
+        assert_eq!(
+            log.read().iter().filter(|name| **name == "busy").count(),
+            100
+        );
+    }
+
+    async fn child_task(name: &'static str, log: Arc<RwLock<Vec<&'static str>>>) {
+        log.write().push(name);
+    }
+
+    async fn parent_task(spawner: Spawner, log: Arc<RwLock<Vec<&'static str>>>) {
+        spawner.spawn(Task::new(child_task("first child", log.clone())));
+        spawner.spawn(Task::new(child_task("second child", log.clone())));
+        log.write().push("parent");
+    }
+
+    #[test]
+    fn test_spawner() {
+        let mut executor = DeterministicExecutor::new();
+        let log = Arc::new(RwLock::new(Vec::new()));
+
+        executor.spawn(Task::new(parent_task(executor.spawner(), log.clone())));
+        executor.run();
+
+        assert_eq!(*log.read(), vec!["parent", "first child", "second child"]);
+        assert!(executor.tasks.is_empty());
+    }
+
+    /// A task spawning its successor, `remaining` times.
+    fn respawning_task(
+        spawner: Spawner,
+        remaining: usize,
+        log: Arc<RwLock<Vec<&'static str>>>,
+    ) -> Task {
+        Task::new(async move {
+            log.write().push("respawning");
+            if remaining > 0 {
+                spawner.spawn(respawning_task(spawner.clone(), remaining - 1, log.clone()));
+            }
+        })
+    }
+
+    #[test]
+    fn test_no_starvation() {
+        let mut executor = DeterministicExecutor::new();
+        let log = Arc::new(RwLock::new(Vec::new()));
+
+        executor.spawn(respawning_task(executor.spawner(), 100, log.clone()));
+        executor.spawn(Task::new(yielding_task("other", 3, log.clone())));
+
+        // the other task progresses every round, while new work keeps being spawned
+        for round in 0..3 {
+            executor.run_ready_tasks();
+            let other = log.read().iter().filter(|name| **name == "other").count();
+            assert_eq!(other, round + 1, "other task starved on round {}", round);
+        }
+        executor.run();
+        assert_eq!(log.read().len(), 101 + 3);
+    }
+
+    #[test]
+    fn test_deadlock() {
+        let mut executor = DeterministicExecutor::new();
+        executor.spawn(Task::new(example_task()));
+        executor.spawn(Task::new(std::future::pending()));
+
+        match executor.try_run() {
+            Err(SimError::Deadlock) => {}
+            other => panic!("expected a deadlock, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_run_to_quiescence() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let time = reactor.get_deterministic_time();
+        let state = Arc::new(RwLock::new(Vec::new()));
+
+        for minutes in [1, 5, 3] {
+            executor.spawn(Task::new(example_state_task(
+                reactor.clone(),
+                time.clone(),
+                Duration::from_secs(minutes * 60),
+                state.clone(),
+            )));
+        }
+        // a wait outliving every task
+        reactor.register_wait(Duration::from_secs(10 * 60), futures::task::noop_waker());
+
+        assert_eq!(
+            executor
+                .run_to_quiescence()
+                .expect("simulation should settle"),
+            Duration::from_secs(10 * 60)
+        );
+        assert_eq!(state.read().len(), 3);
+        assert_eq!(reactor.pending_waits(), 0);
+
+        executor.spawn(Task::new(std::future::pending()));
+        assert!(matches!(
+            executor.run_to_quiescence(),
+            Err(SimError::Deadlock)
+        ));
+    }
+
+    #[test]
+    fn test_run_report() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let thirty_years = Duration::from_secs(60 * 60 * 24 * 365 * 30);
+        executor.spawn(Task::new(example_state_task(
+            reactor.clone(),
+            reactor.get_deterministic_time(),
+            thirty_years,
+            Arc::new(RwLock::new(Vec::new())),
+        )));
+
+        let report = executor.run();
+        assert_eq!(report.simulated, thirty_years);
+        assert!(report.real < Duration::from_secs(1));
+        assert!(report.speedup() > 1_000_000.0, "{}", report);
+    }
+
+    #[test]
+    fn test_pacing() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        executor.pace(0.000_001);
+        let state = Arc::new(RwLock::new(Vec::new()));
+
+        executor.spawn(Task::new(example_state_task(
+            reactor.clone(),
+            reactor.get_deterministic_time(),
+            Duration::from_secs(3600),
+            state.clone(),
+        )));
+
+        let start = Instant::now();
+        executor.run();
+        // an hour of simulation is paced to a few milliseconds
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(state.read().len(), 1);
+    }
+
+    #[test]
+    fn test_pacing_factor() {
+        let mut executor = DeterministicExecutor::new();
+        for invalid in [-1.0, f64::NAN] {
+            executor.pace(invalid);
+            assert_eq!(executor.pacing, None);
+        }
+        // an infinite factor is valid, each pause saturates
+        executor.pace(f64::INFINITY);
+        assert_eq!(executor.pacing, Some(f64::INFINITY));
+        assert_eq!(paced(Duration::from_secs(1), f64::INFINITY), Duration::MAX);
+        assert_eq!(paced(Duration::MAX, 2.0), Duration::MAX);
+        assert_eq!(
+            paced(Duration::from_secs(1), 0.5),
+            Duration::from_millis(500)
+        );
+    }
+
+    /// A future keeping its waker, completing on the first poll.
+    struct StashWaker {
+        stash: Arc<RwLock<Option<Waker>>>,
+    }
+
+    impl Future for StashWaker {
+        type Output = ();
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            *self.stash.write() = Some(cx.waker().clone());
+            Poll::Ready(())
+        }
+    }
+
+    #[test]
+    fn test_stale_waker() {
+        let mut executor = DeterministicExecutor::new();
+        let stash = Arc::new(RwLock::new(None));
+        executor.spawn(Task::new(StashWaker {
+            stash: stash.clone(),
+        }));
+        executor.run();
+
+        let waker = stash.write().take().expect("waker was not stashed");
+        // the task is done, waking it is a no-op for the next run
+        waker.wake_by_ref();
+        executor.run();
+        assert!(executor.tasks.is_empty());
+
+        drop(executor);
+        waker.wake();
+    }
+
+    fn bounded(capacity: usize, overflow: Overflow) -> DeterministicExecutor {
+        DeterministicExecutor::builder()
+            .queue(QueuePolicy::Bounded { capacity, overflow })
+            .build()
+    }
+
+    #[test]
+    fn test_unbounded_queue() {
+        let mut executor = DeterministicExecutor::builder()
+            .queue(QueuePolicy::Unbounded)
+            .build();
+        let log = Arc::new(RwLock::new(Vec::new()));
+        for _ in 0..1000 {
+            executor.spawn(Task::new(child_task("child", log.clone())));
+        }
+        executor.run();
+        assert_eq!(log.read().len(), 1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "task queue is full")]
+    fn test_bounded_queue_panic() {
+        let mut executor = bounded(2, Overflow::Panic);
+        for _ in 0..3 {
+            executor.spawn(Task::new(example_task()));
+        }
+    }
+
+    #[test]
+    fn test_bounded_queue_drop() {
+        let mut executor = bounded(2, Overflow::Drop);
+        let log = Arc::new(RwLock::new(Vec::new()));
+        for name in ["first", "second", "third"] {
+            executor.spawn(Task::new(child_task(name, log.clone())));
+        }
+        executor.run();
+        assert_eq!(*log.read(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_bounded_queue_backpressure() {
+        let mut executor = bounded(2, Overflow::Backpressure);
+        let reactor = executor.reactor();
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+        let spawned = Arc::new(RwLock::new(Vec::new()));
+        // children alive at the same time, and the most ever seen
+        let alive = Arc::new(RwLock::new((0, 0)));
+
+        let spawner = executor.spawner();
+        let log = spawned.clone();
+        let children = alive.clone();
+        executor.spawn(Task::new(async move {
+            for _ in 0..3 {
+                // the parent is taking a slot, so children are running one at a time
+                let (child_time, child_reactor) = (time.clone(), reactor.clone());
+                let alive = children.clone();
+                let child = async move {
+                    {
+                        let mut alive = alive.write();
+                        alive.0 += 1;
+                        alive.1 = alive.1.max(alive.0);
+                    }
+                    let one_second = Duration::from_secs(1);
+                    DeterministicTimer::wait_with_reactor(child_time, child_reactor, one_second)
+                        .await;
+                    alive.write().0 -= 1;
+                };
+                spawner.spawn_async(Task::new(child)).await;
+                log.write().push(time.now().duration_since(start));
+            }
+        }));
+        executor.run();
+
+        assert_eq!(
+            *spawned.read(),
+            vec![
+                Duration::ZERO,
+                Duration::from_secs(1),
+                Duration::from_secs(2)
+            ]
+        );
+        assert_eq!(*alive.read(), (0, 1));
+    }
+
+    #[test]
+    fn test_independent_reactors() {
+        let mut first = DeterministicExecutor::new();
+        let mut second = DeterministicExecutor::new();
+        let first_time = first.reactor().get_deterministic_time();
+        let second_time = second.reactor().get_deterministic_time();
+        let (first_start, second_start) = (first_time.now(), second_time.now());
+
+        let state = Arc::new(RwLock::new(Vec::new()));
+        first.spawn(Task::new(example_state_task(
+            first.reactor(),
+            first_time.clone(),
+            Duration::from_secs(60),
+            state.clone(),
+        )));
+        second.spawn(Task::new(example_state_task(
+            second.reactor(),
+            second_time.clone(),
+            Duration::from_secs(3600),
+            state.clone(),
+        )));
+
+        first.run();
+        assert_eq!(first_time.now() - first_start, Duration::from_secs(60));
+        assert_eq!(second_time.now(), second_start, "second timeline moved");
+
+        second.run();
+        assert_eq!(second_time.now() - second_start, Duration::from_secs(3600));
+        assert_eq!(first_time.now() - first_start, Duration::from_secs(60));
+        assert_eq!(state.read().len(), 2);
+    }
+
+    #[test]
+    fn test_task_ids_per_executor() {
+        let mut first = DeterministicExecutor::new();
+        let mut second = DeterministicExecutor::new();
+        let log = Arc::new(RwLock::new(Vec::new()));
+
+        // both executors are giving the same IDs, without colliding
+        for _ in 0..100 {
+            first.spawn(Task::new(child_task("first", log.clone())));
+            second.spawn(Task::new(child_task("second", log.clone())));
+        }
+        assert_eq!(
+            first.tasks.keys().collect::<Vec<_>>(),
+            second.tasks.keys().collect::<Vec<_>>()
+        );
+
+        first.run_ready_tasks();
+        second.spawn(Task::new(child_task("second", log.clone())));
+        first.spawn(Task::new(child_task("first", log.clone())));
+        first.run();
+        second.run();
+
+        let count = |name| log.read().iter().filter(|entry| **entry == name).count();
+        assert_eq!(count("first"), 101);
+        assert_eq!(count("second"), 101);
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Lifecycle {
+        Spawn(TaskId),
+        Poll(TaskId),
+        Complete(TaskId),
+        Advance(Duration),
+    }
+
+    struct Recorder(Arc<RwLock<Vec<Lifecycle>>>);
+
+    impl ExecutorObserver for Recorder {
+        fn on_spawn(&mut self, id: TaskId) {
+            self.0.write().push(Lifecycle::Spawn(id));
+        }
+        fn on_poll(&mut self, id: TaskId) {
+            self.0.write().push(Lifecycle::Poll(id));
+        }
+        fn on_complete(&mut self, id: TaskId) {
+            self.0.write().push(Lifecycle::Complete(id));
+        }
+        fn on_advance(&mut self, from: Instant, to: Instant) {
+            self.0.write().push(Lifecycle::Advance(to - from));
+        }
+    }
+
+    #[test]
+    fn test_observer() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let events = Arc::new(RwLock::new(Vec::new()));
+        executor.observe(Recorder(events.clone()));
+
+        executor.spawn(Task::new(example_task()));
+        executor.spawn(Task::new(example_state_task(
+            reactor.clone(),
+            reactor.get_deterministic_time(),
+            Duration::from_secs(1),
+            Arc::new(RwLock::new(Vec::new())),
+        )));
+        executor.run();
+
+        let (first, second) = (TaskId::new(0), TaskId::new(1));
+        assert_eq!(
+            *events.read(),
+            vec![
+                Lifecycle::Spawn(first),
+                Lifecycle::Spawn(second),
+                Lifecycle::Poll(first),
+                Lifecycle::Complete(first),
+                Lifecycle::Poll(second),
+                Lifecycle::Advance(Duration::from_secs(1)),
+                Lifecycle::Poll(second),
+                Lifecycle::Complete(second),
+            ]
+        );
+    }
+
+    /// runs two tasks yielding in turn, returns the order they were polled in
+    fn interleaving(seed: u64) -> Vec<&'static str> {
+        let mut executor = DeterministicExecutor::builder().buggify_order(seed).build();
+        let log = Arc::new(RwLock::new(Vec::new()));
+        executor.spawn(Task::new(yielding_task("first", 5, log.clone())));
+        executor.spawn(Task::new(yielding_task("second", 5, log.clone())));
+        executor.run();
+        let order = log.read().clone();
+        order
+    }
+
+    #[test]
+    fn test_buggify_order() {
+        let interleavings: BTreeSet<Vec<&'static str>> = (0..20).map(interleaving).collect();
+        assert!(
+            interleavings.len() > 1,
+            "the order did not change across seeds"
+        );
+        for seed in 0..20 {
+            assert_eq!(interleaving(seed), interleaving(seed));
+        }
+
+        // without reordering, the tasks are polled in turn
+        let mut executor = DeterministicExecutor::new();
+        let log = Arc::new(RwLock::new(Vec::new()));
+        executor.spawn(Task::new(yielding_task("first", 5, log.clone())));
+        executor.spawn(Task::new(yielding_task("second", 5, log.clone())));
+        executor.run();
+        assert_eq!(*log.read(), ["first", "second"].repeat(5));
+    }
+
+    #[test]
+    fn test_dump_state() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        executor.spawn(Task::new(example_state_task(
+            reactor.clone(),
+            reactor.get_deterministic_time(),
+            Duration::from_secs(1),
+            Arc::new(RwLock::new(Vec::new())),
+        )));
+        executor.spawn(Task::new(example_task()));
+        assert_eq!(executor.task_ids(), vec![TaskId::new(0), TaskId::new(1)]);
+        assert_eq!(
+            executor.dump_state(),
+            "tasks (2): TaskId(0), TaskId(1)\nqueue (2): TaskId(0), TaskId(1)\nreactor waits (0): \n"
+        );
+        // the queue is not drained by a dump
+        assert_eq!(executor.dump_state(), executor.dump_state());
+
+        executor.run_ready_tasks();
+        assert_eq!(executor.task_ids(), vec![TaskId::new(0)]);
+        assert_eq!(
+            executor.dump_state(),
+            "tasks (1): TaskId(0)\nqueue (0): \nreactor waits (1): in 1s\n"
+        );
+        // dumping the state does not change it
+        assert_eq!(executor.dump_state(), executor.dump_state());
+
+        executor.run();
+        assert!(executor.task_ids().is_empty());
+    }
+
+    #[test]
+    fn test_poll_count() {
+        let mut executor = DeterministicExecutor::builder()
+            .warn_polls_above(10)
+            .build();
+        executor.spawn(Task::new(YieldNow { remaining: 25 }));
+        executor.spawn(Task::new(async {}));
+        let (busy, idle) = match executor.task_ids()[..] {
+            [busy, idle] => (busy, idle),
+            _ => unreachable!(),
+        };
+        executor.run();
+
+        // polled once per wake, and once more to complete
+        assert_eq!(executor.poll_count(busy), 26);
+        assert_eq!(executor.poll_count(idle), 1);
+        assert_eq!(executor.poll_count(TaskId::new(42)), 0);
+    }
+
+    /// A future parking its waker on the first poll, and completing on the next one.
+    struct Park {
+        parked: Arc<RwLock<Vec<Waker>>>,
+        done: bool,
+    }
+
+    impl Future for Park {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.done {
+                return Poll::Ready(());
+            }
+            self.done = true;
+            self.parked.write().push(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_wake_order() {
+        let mut executor = DeterministicExecutor::new();
+        let events = Arc::new(RwLock::new(Vec::new()));
+        let parked = Arc::new(RwLock::new(Vec::new()));
+        for _ in 0..3 {
+            executor.spawn(Task::new(Park {
+                parked: parked.clone(),
+                done: false,
+            }));
+        }
+        executor.run_ready_tasks();
+        executor.observe(Recorder(events.clone()));
+
+        // waking in the reverse order of the IDs, the second task twice
+        let wakers = std::mem::take(&mut *parked.write());
+        for index in [2, 1, 0, 1] {
+            wakers[index].wake_by_ref();
+        }
+        executor.run_ready_tasks();
+
+        let polled: Vec<TaskId> = events
+            .read()
+            .iter()
+            .filter_map(|event| match event {
+                Lifecycle::Poll(id) => Some(*id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(polled, vec![TaskId::new(2), TaskId::new(1), TaskId::new(0)]);
+        assert!(executor.tasks.is_empty());
+    }
+
+    #[test]
+    fn test_non_send_futures() {
+        let mut executor = DeterministicExecutor::new();
+        let reactor = executor.reactor();
+        let spawner = executor.spawner();
+        let counter = Rc::new(RefCell::new(0));
+
+        for delay in [2, 1] {
+            let (time, reactor) = (reactor.get_deterministic_time(), reactor.clone());
+            let counter = counter.clone();
+            let spawner = spawner.clone();
+            executor.spawn(Task::new(async move {
+                DeterministicTimer::wait_with_reactor(time, reactor, Duration::from_secs(delay))
+                    .await;
+                *counter.borrow_mut() += delay;
+                // the `Rc` can also be moved into a task spawned from a task
+                spawner.spawn(Task::new(async move {
+                    *counter.borrow_mut() *= 10;
+                }));
+            }));
+        }
+        executor.run();
+        // (1 * 10 + 2) * 10
+        assert_eq!(*counter.borrow(), 120);
+    }
+
+    #[test]
+    fn test_spawn_after() {
+        let mut executor = DeterministicExecutor::new();
+        let time = executor.reactor().get_deterministic_time();
+        let start = time.now();
+        let starts = Rc::new(RefCell::new(Vec::new()));
+
+        let record = |name: &'static str| {
+            let (time, starts) = (time.clone(), starts.clone());
+            async move {
+                let elapsed = time.now().duration_since(start);
+                starts.borrow_mut().push((name, elapsed));
+            }
+        };
+        for (name, delay) in [("third", 3), ("first", 1), ("second", 2)] {
+            executor.spawn_after(Duration::from_secs(delay), record(name));
+        }
+        // the delay of a task spawned from a task starts when it is spawned
+        let spawner = executor.spawner();
+        let nested = record("nested");
+        executor.spawn_after(Duration::from_secs(2), async move {
+            spawner.spawn_after(Duration::from_millis(500), nested);
+        });
+        executor.run();
+
+        let second = Duration::from_secs(1);
+        assert_eq!(
+            *starts.borrow(),
+            vec![
+                ("first", second),
+                ("second", second * 2),
+                ("nested", second * 5 / 2),
+                ("third", second * 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ready_task_waker() {
+        let mut executor = DeterministicExecutor::new();
+        let reactor = executor.reactor();
+        let time = reactor.get_deterministic_time();
+        executor.spawn(Task::new(async {}));
+        executor.spawn(Task::new(async move {
+            DeterministicTimer::wait_with_reactor(time, reactor, Duration::from_secs(1)).await;
+        }));
+
+        executor.run_ready_tasks();
+        // only the pending task has a cached waker
+        assert_eq!(executor.tasks.len(), 1);
+        assert_eq!(
+            executor.waker_cache.keys().copied().collect::<Vec<_>>(),
+            vec![TaskId::new(1)]
+        );
+
+        executor.run();
+        assert!(executor.waker_cache.is_empty());
+        assert_eq!(executor.poll_count(TaskId::new(0)), 1);
+    }
+
+    #[test]
+    fn test_buggify_mode() {
+        let normal = DeterministicExecutor::builder()
+            .buggify_mode(BuggifyMode::Normal, 42)
+            .build();
+        assert!(normal.order.is_none());
+        let chaos = DeterministicExecutor::builder()
+            .buggify_mode(BuggifyMode::chaos(), 42)
+            .build();
+        assert!(chaos.order.is_some());
+    }
+
+    #[test]
+    fn test_report_progress() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let recorded = reports.clone();
+        executor.report_progress(3, move |progress| recorded.borrow_mut().push(progress));
+
+        let time = reactor.get_deterministic_time();
+        executor.spawn(Task::new(async move {
+            for _ in 0..10 {
+                DeterministicTimer::wait_with_reactor(
+                    time.clone(),
+                    reactor.clone(),
+                    Duration::from_secs(1),
+                )
+                .await;
+            }
+        }));
+        executor.run();
+
+        // ten advances of one second, reported every three
+        let progress = |advances| Progress {
+            advances,
+            simulated: Duration::from_secs(advances),
+            tasks: 1,
+        };
+        assert_eq!(
+            *reports.borrow(),
+            vec![progress(3), progress(6), progress(9)]
+        );
+    }
 }
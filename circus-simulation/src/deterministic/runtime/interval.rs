@@ -0,0 +1,81 @@
+//! Interval module
+
+use crate::deterministic::runtime::reactor::DeterministicReactor;
+use crate::deterministic::runtime::timer::DeterministicTimer;
+use crate::deterministic::time::DeterministicTime;
+use futures::{Future, Stream};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// A stream yielding every `period` of simulated time. It can be used with the
+/// combinators of `futures::StreamExt`, and dropping it cancels the next tick.
+pub struct IntervalStream {
+    time: DeterministicTime,
+    reactor: DeterministicReactor,
+    period: Duration,
+    timer: DeterministicTimer,
+}
+
+impl IntervalStream {
+    /// creates a new `IntervalStream`, the first tick happens after `period`
+    pub fn new(
+        time: DeterministicTime,
+        reactor: DeterministicReactor,
+        period: Duration,
+    ) -> IntervalStream {
+        let timer = DeterministicTimer::wait_with_reactor(time.clone(), reactor.clone(), period);
+        IntervalStream {
+            time,
+            reactor,
+            period,
+            timer,
+        }
+    }
+}
+
+impl Stream for IntervalStream {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.timer).poll(cx) {
+            Poll::Ready(()) => {
+                tracing::trace!("interval of {:?} ticked", self.period);
+                self.timer = DeterministicTimer::wait_with_reactor(
+                    self.time.clone(),
+                    self.reactor.clone(),
+                    self.period,
+                );
+                Poll::Ready(Some(()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::interval::IntervalStream;
+    use crate::deterministic::runtime::reactor::DeterministicReactor;
+    use crate::deterministic::runtime::task::Task;
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    #[test]
+    fn test_interval_stream() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+
+        let stream = IntervalStream::new(time.clone(), reactor, Duration::from_secs(10));
+        executor.spawn(Task::new(async move {
+            let ticks: Vec<()> = stream.take(3).collect().await;
+            assert_eq!(ticks.len(), 3);
+        }));
+        executor.run();
+
+        assert_eq!(time.now().duration_since(start), Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,179 @@
+//! Join module
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Future returned by [`join_all`]
+pub struct JoinAll<F: Future> {
+    futures: Vec<MaybeDone<F>>,
+}
+
+enum MaybeDone<F: Future> {
+    Pending(Pin<Box<F>>),
+    Done(F::Output),
+    Taken,
+}
+
+/// Waits for every future to complete, and returns their outputs in input order.
+/// Each time it is polled, the pending futures are polled in input order, whatever
+/// the order their wakers fired, so the outcome does not depend on the wake order.
+/// Tasks spawned by the futures through a
+/// [`Spawner`](crate::deterministic::runtime::executor::Spawner) are therefore spawned in input
+/// order too, and are polled on the next round of the executor, after the tasks already woken.
+pub fn join_all<I>(futures: I) -> JoinAll<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    JoinAll {
+        futures: futures
+            .into_iter()
+            .map(|future| MaybeDone::Pending(Box::pin(future)))
+            .collect(),
+    }
+}
+
+// the futures are boxed, and the outputs are never pinned
+impl<F: Future> Unpin for JoinAll<F> {}
+
+impl<F: Future> Future for JoinAll<F> {
+    type Output = Vec<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut done = true;
+        for slot in self.futures.iter_mut() {
+            if let MaybeDone::Pending(future) = slot {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(output) => *slot = MaybeDone::Done(output),
+                    Poll::Pending => done = false,
+                }
+            }
+        }
+        if !done {
+            return Poll::Pending;
+        }
+
+        let outputs = self
+            .futures
+            .iter_mut()
+            .map(|slot| match std::mem::replace(slot, MaybeDone::Taken) {
+                MaybeDone::Done(output) => output,
+                _ => panic!("JoinAll polled after completion"),
+            })
+            .collect();
+        Poll::Ready(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::random::DeterministicRandom;
+    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::join::join_all;
+    use crate::deterministic::runtime::task::Task;
+    use crate::deterministic::runtime::timer::DeterministicTimer;
+    use parking_lot::RwLock;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_join_all_ready() {
+        let ready = ["first", "second", "third"].map(std::future::ready);
+        let outputs = futures::executor::block_on(join_all(ready));
+        assert_eq!(outputs, vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn test_join_all_order() {
+        for seed in 0..10 {
+            let mut executor = DeterministicExecutor::new();
+            let reactor = executor.reactor();
+            let time = reactor.get_deterministic_time();
+            let state = Arc::new(RwLock::new(Vec::new()));
+
+            // futures completing in a random order
+            let mut random = DeterministicRandom::new_with_seed(seed);
+            let waits = (0..3).map(|index| {
+                let delay = Duration::from_millis(random.random_between(0..3));
+                let timer =
+                    DeterministicTimer::wait_with_reactor(time.clone(), reactor.clone(), delay);
+                async move {
+                    timer.await;
+                    index
+                }
+            });
+            let joined = join_all(waits.collect::<Vec<_>>());
+
+            let outputs = state.clone();
+            executor.spawn(Task::new(async move {
+                *outputs.write() = joined.await;
+            }));
+            executor.run();
+            assert_eq!(
+                *state.read(),
+                vec![0, 1, 2],
+                "wrong order for seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn test_join_all_spawning() {
+        for seed in 0..10 {
+            let mut executor = DeterministicExecutor::new();
+            let reactor = executor.reactor();
+            let time = reactor.get_deterministic_time();
+            let spawner = executor.spawner();
+            let log = Arc::new(RwLock::new(Vec::new()));
+
+            // each joined future spawns a child once its random delay elapsed
+            let mut random = DeterministicRandom::new_with_seed(seed);
+            let delays: Vec<u64> = (0..4).map(|_| random.random_between(0..3)).collect();
+            let fan_out = delays.iter().enumerate().map(|(index, delay)| {
+                let timer = DeterministicTimer::wait_with_reactor(
+                    time.clone(),
+                    reactor.clone(),
+                    Duration::from_millis(*delay),
+                );
+                let spawner = spawner.clone();
+                let log = log.clone();
+                async move {
+                    timer.await;
+                    let child_log = log.clone();
+                    spawner.spawn(Task::new(async move {
+                        child_log.write().push(format!("child {}", index));
+                    }));
+                    log.write().push(format!("parent {}", index));
+                }
+            });
+            let joined = join_all(fan_out.collect::<Vec<_>>());
+
+            let parent_log = log.clone();
+            executor.spawn(Task::new(async move {
+                joined.await;
+                parent_log.write().push(String::from("joined"));
+            }));
+            executor.run();
+
+            // parents woken at the same instant run in input order, then their children
+            // run on the next round, in the same order
+            let mut rounds: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+            for (index, delay) in delays.iter().enumerate() {
+                rounds.entry(*delay).or_default().push(index);
+            }
+            let last = rounds.len() - 1;
+            let mut expected = Vec::new();
+            for (round, indexes) in rounds.values().enumerate() {
+                expected.extend(indexes.iter().map(|index| format!("parent {}", index)));
+                if round == last {
+                    expected.push(String::from("joined"));
+                }
+                expected.extend(indexes.iter().map(|index| format!("child {}", index)));
+            }
+            assert_eq!(*log.read(), expected, "wrong order for seed {}", seed);
+        }
+    }
+}
@@ -4,7 +4,14 @@
 //  * https://github.com/enlightware/simple-async-local-executor/blob/main/src/lib.rs
 //  * https://os.phil-opp.com/async-await
 
+pub mod actor;
+pub mod channel;
+pub mod event;
 pub mod executor;
+pub mod interval;
+pub mod join;
 pub mod reactor;
+pub mod select;
 pub mod task;
+pub mod timeout;
 pub(crate) mod timer;
@@ -1,12 +1,13 @@
 //! Reactor module
 
-use crate::deterministic::time::DeterministicTime;
+use crate::deterministic::time::{saturating_add, DeterministicTime};
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use std::cmp::Ordering;
+use std::sync::atomic::{self, AtomicU64};
 use std::sync::Arc;
 use std::task::Waker;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// The DeterministicReactor is used to simulate "real I/O". It is only compatible with
 /// simulation structures, as they cooperate with him. Instead of registering I/O to a loop,
@@ -16,14 +17,20 @@ use std::time::Duration;
 pub struct DeterministicReactor {
     time: DeterministicTime,
     waits: Arc<Mutex<Vec<ReactorEntry>>>,
+    next_wait: Arc<AtomicU64>,
 }
 
+/// Identifies a wait registered on a [`DeterministicReactor`], to cancel it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WaitId(u64);
+
 impl Default for DeterministicReactor {
     /// Create a default `DeterministicReactor`
     fn default() -> DeterministicReactor {
         DeterministicReactor {
             time: DeterministicTime::new(),
             waits: Arc::new(Mutex::new(vec![])),
+            next_wait: Arc::new(AtomicU64::new(0)),
         }
     }
 }
@@ -41,61 +48,150 @@ impl DeterministicReactor {
         self.time.clone()
     }
 
-    /// Register a wait
-    pub fn register_wait(&self, duration: Duration, waker: Waker) {
+    /// Register a wait, the waker will be woken once the simulated time has advanced
+    /// by `duration` from now. A zero-duration wait is woken right away, without
+    /// going through [`DeterministicReactor::advance_simulation`].
+    /// Returns the ID of the registered wait, or `None` if it was woken right away.
+    pub fn register_wait(&self, duration: Duration, waker: Waker) -> Option<WaitId> {
+        if duration.is_zero() {
+            tracing::trace!("waking a zero-duration wait");
+            waker.wake();
+            return None;
+        }
         tracing::trace!("registering a wait for {:?}", duration);
-        self.waits.lock().push(ReactorEntry::new(duration, waker));
+        let deadline = saturating_add(self.time.now(), duration, |now, duration| {
+            now.checked_add(duration)
+        });
+        let id = WaitId(self.next_wait.fetch_add(1, atomic::Ordering::Relaxed));
+        self.waits
+            .lock()
+            .push(ReactorEntry::new(id, deadline, duration, waker));
+        Some(id)
+    }
+
+    /// Returns whether a wait did not fire yet
+    pub(crate) fn is_pending(&self, id: WaitId) -> bool {
+        self.waits.lock().iter().any(|entry| entry.id == id)
+    }
+
+    /// Removes a wait that did not fire yet, so the simulation does not advance to its deadline.
+    /// Returns whether the wait was still pending.
+    pub fn cancel_wait(&self, id: WaitId) -> bool {
+        let mut lock = self.waits.lock();
+        match lock.iter().position(|entry| entry.id == id) {
+            Some(position) => {
+                let entry = lock.remove(position);
+                tracing::trace!("cancelling a wait of {:?}", entry.duration);
+                true
+            }
+            None => false,
+        }
     }
 
-    /// Advancing simulation. It will chose the next Instant stored in  `waits` and apply it
-    /// on the deterministicTime.
+    /// Returns the number of registered waits that did not fire yet
+    pub fn pending_waits(&self) -> usize {
+        self.waits.lock().len()
+    }
+
+    /// Returns the time left before each registered wait fires, in deadline order
+    pub(crate) fn pending_remaining(&self) -> Vec<Duration> {
+        let now = self.time.now();
+        let mut remaining: Vec<Duration> = self
+            .waits
+            .lock()
+            .iter()
+            .map(|entry| entry.deadline.saturating_duration_since(now))
+            .collect();
+        remaining.sort();
+        remaining
+    }
+
+    /// Returns the earliest deadline among the registered waits, without advancing the simulation
+    pub fn peek_next_deadline(&self) -> Option<Instant> {
+        self.waits.lock().iter().map(|entry| entry.deadline).min()
+    }
+
+    /// Advancing simulation. It will chose the next deadline stored in `waits`, advance the
+    /// deterministicTime up to it, and wake the associated waker.
+    /// Waits are ordered by absolute deadline, so a shorter wait registered later can fire
+    /// before an older, longer one.
+    /// Returns how much the simulated time advanced, which is zero when the wait that fired
+    /// was due at the current instant, or `None` if there was no wait to fire.
     pub fn advance_simulation(&self) -> Option<Duration> {
         let mut lock = self.waits.lock();
         if !lock.is_empty() {
-            // sort entry per duration
+            // sort entry per deadline, the sort is stable so registration order is kept on ties
             lock.sort();
 
             // get next wait
             let next = lock.remove(0);
 
             tracing::trace!("advancing from {:?}", next.duration);
-            self.time.advance(next.duration);
+            let now = self.time.now();
+            self.time
+                .advance(next.deadline.saturating_duration_since(now));
             next.waker.wake();
-            Some(next.duration)
+            Some(self.time.now().saturating_duration_since(now))
         } else {
             None
         }
     }
+
+    /// Advance the simulation by exactly `duration`, waking every wait whose deadline is
+    /// reached, in deadline order. Returns the number of woken waits.
+    pub fn advance_by(&self, duration: Duration) -> usize {
+        let mut lock = self.waits.lock();
+        self.time.advance(duration);
+        let now = self.time.now();
+
+        lock.sort();
+        let due = lock
+            .iter()
+            .take_while(|entry| entry.deadline <= now)
+            .count();
+        for entry in lock.drain(..due) {
+            tracing::trace!("firing wait of {:?}", entry.duration);
+            entry.waker.wake();
+        }
+        due
+    }
 }
 
 #[doc(hidden)]
 struct ReactorEntry {
+    id: WaitId,
+    deadline: Instant,
     duration: Duration,
     waker: Waker,
 }
 
 impl ReactorEntry {
-    pub fn new(duration: Duration, waker: Waker) -> ReactorEntry {
-        ReactorEntry { duration, waker }
+    pub fn new(id: WaitId, deadline: Instant, duration: Duration, waker: Waker) -> ReactorEntry {
+        ReactorEntry {
+            id,
+            deadline,
+            duration,
+            waker,
+        }
     }
 }
 
 impl PartialOrd for ReactorEntry {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.duration.partial_cmp(&other.duration)
+        Some(self.cmp(other))
     }
 }
 
 impl PartialEq for ReactorEntry {
     fn eq(&self, other: &Self) -> bool {
-        self.duration.eq(&other.duration)
+        self.deadline.eq(&other.deadline)
     }
 }
 
 impl Eq for ReactorEntry {}
 impl Ord for ReactorEntry {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.duration.cmp(&other.duration)
+        self.deadline.cmp(&other.deadline)
     }
 }
 #[cfg(test)]
@@ -163,9 +259,137 @@ mod tests {
         let waker_2 = Waker::from(Arc::new(fake_waker_2));
         reactor.register_wait(Duration::from_secs(1), waker_2);
 
-        // draining
+        // draining, the second wait is due 9s after the first one
         assert_eq!(reactor.advance_simulation(), Some(Duration::from_secs(1)));
-        assert_eq!(reactor.advance_simulation(), Some(Duration::from_secs(10)));
+        assert_eq!(reactor.advance_simulation(), Some(Duration::from_secs(9)));
+        assert_eq!(reactor.advance_simulation(), None);
+    }
+
+    #[test]
+    fn test_advance_by() {
+        let reactor = DeterministicReactor::default();
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+
+        let wakers: Vec<Arc<FakeWaker>> = (0..3).map(|_| Arc::new(FakeWaker::default())).collect();
+        for (waker, seconds) in wakers.iter().zip([1, 3, 7]) {
+            reactor.register_wait(Duration::from_secs(seconds), Waker::from(waker.clone()));
+        }
+
+        assert_eq!(reactor.advance_by(Duration::from_secs(5)), 2);
+        assert_eq!(time.now().duration_since(start), Duration::from_secs(5));
+        assert!(wakers[0].triggered.load(Ordering::Relaxed));
+        assert!(wakers[1].triggered.load(Ordering::Relaxed));
+        assert!(!wakers[2].triggered.load(Ordering::Relaxed));
+
+        // the remaining wait is still due at 7s
+        assert_eq!(reactor.advance_simulation(), Some(Duration::from_secs(2)));
+        assert_eq!(time.now().duration_since(start), Duration::from_secs(7));
+        assert!(wakers[2].triggered.load(Ordering::Relaxed));
+        assert_eq!(reactor.advance_by(Duration::from_secs(5)), 0);
+    }
+
+    #[test]
+    fn test_peek_next_deadline() {
+        let reactor = DeterministicReactor::default();
+        let start = reactor.get_deterministic_time().now();
+        assert_eq!(reactor.peek_next_deadline(), None);
+
+        for seconds in [5, 2, 9] {
+            let waker = Waker::from(Arc::new(FakeWaker::default()));
+            reactor.register_wait(Duration::from_secs(seconds), waker);
+        }
+
+        let next = Some(start + Duration::from_secs(2));
+        assert_eq!(reactor.peek_next_deadline(), next);
+        // peeking is not consuming the wait
+        assert_eq!(reactor.peek_next_deadline(), next);
+        assert_eq!(reactor.pending_waits(), 3);
+        assert_eq!(reactor.get_deterministic_time().now(), start);
+
+        reactor.advance_simulation();
+        assert_eq!(
+            reactor.peek_next_deadline(),
+            Some(start + Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_cancel_wait() {
+        let reactor = DeterministicReactor::default();
+        let fake_waker = Arc::new(FakeWaker::default());
+        let short = reactor.register_wait(Duration::from_secs(1), Waker::from(fake_waker.clone()));
+        let long = reactor.register_wait(Duration::from_secs(9), Waker::from(fake_waker));
+
+        assert!(reactor.cancel_wait(long.unwrap()));
+        assert!(!reactor.cancel_wait(long.unwrap()));
+        assert_eq!(reactor.advance_simulation(), Some(Duration::from_secs(1)));
+        assert_eq!(reactor.advance_simulation(), None);
+        // the wait already fired
+        assert!(!reactor.cancel_wait(short.unwrap()));
+    }
+
+    #[test]
+    fn test_zero_duration_wait() {
+        let reactor = DeterministicReactor::default();
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+
+        let fake_waker = Arc::new(FakeWaker::default());
+        reactor.register_wait(Duration::ZERO, Waker::from(fake_waker.clone()));
+
+        assert!(fake_waker.triggered.load(Ordering::Relaxed));
+        assert_eq!(reactor.advance_simulation(), None);
+        assert_eq!(time.now(), start);
+    }
+
+    #[test]
+    fn test_shorter_wait_registered_on_wake() {
+        let reactor = DeterministicReactor::default();
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+        let register = |seconds| {
+            let waker = Waker::from(Arc::new(FakeWaker::default()));
+            reactor.register_wait(Duration::from_secs(seconds), waker);
+        };
+        let elapsed = || time.now().duration_since(start).as_secs();
+
+        // registered at T=0
+        register(5);
+        register(3);
+        register(8);
+        assert_eq!(reactor.advance_simulation(), Some(Duration::from_secs(3)));
+        assert_eq!(elapsed(), 3);
+        assert_eq!(reactor.advance_simulation(), Some(Duration::from_secs(2)));
+        assert_eq!(elapsed(), 5);
+
+        // woken at T=5, due at T=6, before the wait registered at T=0 for 8s
+        register(1);
+        assert_eq!(reactor.advance_simulation(), Some(Duration::from_secs(1)));
+        assert_eq!(elapsed(), 6);
+        assert_eq!(reactor.advance_simulation(), Some(Duration::from_secs(2)));
+        assert_eq!(elapsed(), 8);
+        assert_eq!(reactor.advance_simulation(), None);
+    }
+
+    #[test]
+    fn test_zero_delta_advance() {
+        let reactor = DeterministicReactor::default();
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+
+        // a batch of waits due at the same instant
+        let wakers: Vec<Arc<FakeWaker>> = (0..2).map(|_| Arc::new(FakeWaker::default())).collect();
+        for waker in wakers.iter() {
+            reactor.register_wait(Duration::from_secs(4), Waker::from(waker.clone()));
+        }
+
+        assert_eq!(reactor.advance_simulation(), Some(Duration::from_secs(4)));
+        assert!(wakers[0].triggered.load(Ordering::Relaxed));
+        assert!(!wakers[1].triggered.load(Ordering::Relaxed));
+        assert_eq!(reactor.advance_simulation(), Some(Duration::ZERO));
+        assert!(wakers[1].triggered.load(Ordering::Relaxed));
+        assert_eq!(time.now().duration_since(start), Duration::from_secs(4));
         assert_eq!(reactor.advance_simulation(), None);
     }
 }
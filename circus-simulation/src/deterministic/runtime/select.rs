@@ -0,0 +1,99 @@
+//! Select module
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Future returned by [`select_all`]
+pub struct SelectAll<F> {
+    futures: Vec<F>,
+}
+
+/// Waits for the first future to complete, and returns its output, its index and the remaining
+/// futures. The futures are polled in input order, so when several of them are ready at the
+/// same time, the one with the lowest index wins.
+///
+/// # Panics
+/// Panics if `futures` is empty.
+pub fn select_all<F>(futures: Vec<F>) -> SelectAll<F>
+where
+    F: Future + Unpin,
+{
+    assert!(!futures.is_empty(), "select_all needs at least one future");
+    SelectAll { futures }
+}
+
+impl<F: Future + Unpin> Future for SelectAll<F> {
+    type Output = (F::Output, usize, Vec<F>);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let ready = self
+            .futures
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, future)| match Pin::new(future).poll(cx) {
+                Poll::Ready(output) => Some((index, output)),
+                Poll::Pending => None,
+            });
+
+        match ready {
+            Some((index, output)) => {
+                let mut remaining = std::mem::take(&mut self.futures);
+                drop(remaining.remove(index));
+                Poll::Ready((output, index, remaining))
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::select::select_all;
+    use crate::deterministic::runtime::task::Task;
+    use crate::deterministic::runtime::timer::DeterministicTimer;
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// selects over timers of the given durations, returns the winning index and the remaining
+    fn select_timers(durations: &[u64]) -> (usize, usize, Duration) {
+        let mut executor = DeterministicExecutor::new();
+        let reactor = executor.reactor();
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+        let state = Arc::new(RwLock::new(None));
+
+        let timers = durations
+            .iter()
+            .map(|seconds| {
+                DeterministicTimer::wait_with_reactor(
+                    time.clone(),
+                    reactor.clone(),
+                    Duration::from_secs(*seconds),
+                )
+            })
+            .collect();
+        let result = state.clone();
+        executor.spawn(Task::new(async move {
+            let ((), index, remaining) = select_all(timers).await;
+            *result.write() = Some((index, remaining.len(), time.now().duration_since(start)));
+        }));
+        executor.run();
+
+        let outcome = state.write().take();
+        outcome.expect("select did not complete")
+    }
+
+    #[test]
+    fn test_select_all() {
+        assert_eq!(select_timers(&[3, 1, 2]), (1, 2, Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_select_all_tie() {
+        assert_eq!(select_timers(&[2, 1, 1]), (1, 2, Duration::from_secs(1)));
+        assert_eq!(select_timers(&[0, 0]), (0, 1, Duration::ZERO));
+    }
+}
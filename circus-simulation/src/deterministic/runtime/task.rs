@@ -1,31 +1,28 @@
 //! Task module
 
 use core::{future::Future, pin::Pin};
-use std::sync::atomic::{AtomicU64, Ordering};
 use std::task::{Context, Poll};
 
-/// TaskID
+/// TaskID, given by the executor running the task
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct TaskId(u64);
 
 impl TaskId {
-    fn new() -> Self {
-        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
-        TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    pub(crate) fn new(id: u64) -> Self {
+        TaskId(id)
     }
 }
 
 /// An async Task
 pub struct Task {
-    pub(crate) id: TaskId,
     future: Pin<Box<dyn Future<Output = ()>>>,
 }
 
 impl Task {
-    /// Creates a new task
+    /// Creates a new task. The future does not need to be `Send`, as the executor is polling
+    /// it on a single thread: it can hold an `Rc` or a `RefCell` shared with other tasks.
     pub fn new(future: impl Future<Output = ()> + 'static) -> Task {
         Task {
-            id: TaskId::new(),
             future: Box::pin(future),
         }
     }
@@ -0,0 +1,116 @@
+//! Timeout module
+
+use crate::deterministic::runtime::reactor::DeterministicReactor;
+use crate::deterministic::runtime::timer::DeterministicTimer;
+use crate::deterministic::time::DeterministicTime;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Error returned by [`Timeout`] when the deadline elapsed before the future completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// Future returned by [`timeout`]
+pub struct Timeout<F> {
+    // dropped as soon as the deadline elapsed
+    future: Option<Pin<Box<F>>>,
+    timer: DeterministicTimer,
+}
+
+/// Waits for `future` for at most `duration` of simulated time. When the deadline elapses,
+/// the future is dropped before [`Elapsed`] is returned, cancelling its pending reactor waits,
+/// so a timed out operation does not keep advancing the simulated time.
+/// When the future and the deadline are ready at the same instant, the future wins.
+pub fn timeout<F: Future>(
+    time: DeterministicTime,
+    reactor: DeterministicReactor,
+    duration: Duration,
+    future: F,
+) -> Timeout<F> {
+    Timeout {
+        future: Some(Box::pin(future)),
+        timer: DeterministicTimer::wait_with_reactor(time, reactor, duration),
+    }
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self
+            .future
+            .as_mut()
+            .expect("timeout polled after completion");
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            self.future = None;
+            return Poll::Ready(Ok(output));
+        }
+        match Pin::new(&mut self.timer).poll(cx) {
+            Poll::Ready(()) => {
+                self.future = None;
+                Poll::Ready(Err(Elapsed))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::task::Task;
+    use crate::deterministic::runtime::timeout::{timeout, Elapsed};
+    use crate::deterministic::runtime::timer::DeterministicTimer;
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_timeout() {
+        let mut executor = DeterministicExecutor::new();
+        let reactor = executor.reactor();
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+        let state = Arc::new(RwLock::new(None));
+
+        let results = state.clone();
+        executor.spawn(Task::new(async move {
+            let wait = |duration| {
+                DeterministicTimer::wait_with_reactor(time.clone(), reactor.clone(), duration)
+            };
+            let second = Duration::from_secs(1);
+            // on a tie, the future wins
+            let fast = timeout(time.clone(), reactor.clone(), second, async {
+                wait(second).await;
+                42
+            })
+            .await;
+            let slow = timeout(time.clone(), reactor.clone(), second, async {
+                wait(second * 10).await;
+                42
+            })
+            .await;
+            // the wait of the slow future was cancelled
+            let pending = reactor.pending_waits();
+            *results.write() = Some((fast, slow, pending, time.now().duration_since(start)));
+        }));
+        executor.run();
+
+        let results = state.write().take();
+        assert_eq!(
+            results,
+            Some((Ok(42), Err(Elapsed), 0, Duration::from_secs(2)))
+        );
+    }
+}
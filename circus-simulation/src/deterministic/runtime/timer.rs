@@ -1,20 +1,25 @@
 //! Timer module
 
-use crate::deterministic::runtime::reactor::DeterministicReactor;
-use crate::deterministic::time::DeterministicTime;
+use crate::deterministic::runtime::reactor::{DeterministicReactor, WaitId};
+use crate::deterministic::time::{saturating_add, DeterministicTime};
 use futures::Future;
-use std::ops::Add;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
 
-/// A timer that can be used in simulation
+/// A timer that can be used in simulation. Dropping it before it fires cancels its wait
+/// on the reactor.
+///
+/// The deadline is taken when the timer is created. The first poll registers a wait up to the
+/// deadline and returns `Pending`, and the timer fires on the first poll after the reactor
+/// advanced to its deadline: a timer awaited by a task is polled exactly twice, unless its
+/// deadline is already reached on the first poll.
 pub struct DeterministicTimer {
     time: DeterministicTime,
     duration: Duration,
     expired_at: Instant,
-    // TODO: Once
-    already_registered: bool,
+    // the wait registered on the reactor
+    wait: Option<WaitId>,
     reactor: DeterministicReactor,
 }
 
@@ -29,26 +34,45 @@ impl DeterministicTimer {
         DeterministicTimer {
             time: time.clone(),
             duration,
-            expired_at: time.now().add(duration),
-            already_registered: false,
+            expired_at: saturating_add(time.now(), duration, |now, duration| {
+                now.checked_add(duration)
+            }),
+            wait: None,
             reactor,
         }
     }
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
-        if !self.already_registered {
-            self.reactor
-                .register_wait(self.duration, cx.waker().clone());
-            self.already_registered = true;
-        }
-
         let now = self.time.now();
         tracing::trace!("polling timer, it is now {:?}", now);
         if self.expired_at.le(&now) {
             tracing::trace!("firing timer with {:?}", self.duration);
-            Poll::Ready(())
-        } else {
-            Poll::Pending
+            // the wait fired, or the time was advanced past it
+            if let Some(wait) = self.wait.take() {
+                self.reactor.cancel_wait(wait);
+            }
+            return Poll::Ready(());
+        }
+
+        // the timer may be polled for the first time after its creation, and a drifting clock
+        // may not have reached the deadline once the wait fired, so the wait is registered again
+        let pending = self
+            .wait
+            .map_or(false, |wait| self.reactor.is_pending(wait));
+        if !pending {
+            let remaining = self.expired_at.saturating_duration_since(now);
+            self.wait = self.reactor.register_wait(remaining, cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for DeterministicTimer {
+    fn drop(&mut self) {
+        if let Some(wait) = self.wait.take() {
+            if self.reactor.cancel_wait(wait) {
+                tracing::trace!("dropping timer with {:?} before it fired", self.duration);
+            }
         }
     }
 }
@@ -64,7 +88,7 @@ impl Future for DeterministicTimer {
 mod tests {
     use crate::deterministic::runtime::executor::DeterministicExecutor;
     use crate::deterministic::runtime::reactor::DeterministicReactor;
-    use crate::deterministic::runtime::task::Task;
+    use crate::deterministic::runtime::task::{Task, TaskId};
     use crate::deterministic::runtime::timer::DeterministicTimer;
     use crate::deterministic::time::DeterministicTime;
     use std::time::{Duration, Instant};
@@ -106,4 +130,86 @@ mod tests {
             &Instant::now()
         );
     }
+
+    #[test]
+    fn test_dropped_timer() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let time = reactor.get_deterministic_time();
+
+        let waits = reactor.clone();
+        executor.spawn(Task::new(async move {
+            let short = DeterministicTimer::wait_with_reactor(
+                time.clone(),
+                waits.clone(),
+                Duration::from_secs(1),
+            );
+            let long =
+                DeterministicTimer::wait_with_reactor(time, waits.clone(), Duration::from_secs(60));
+            match futures::future::select(short, long).await {
+                futures::future::Either::Left(((), long)) => {
+                    assert_eq!(waits.pending_waits(), 1);
+                    drop(long);
+                }
+                futures::future::Either::Right(_) => panic!("the long timer won the race"),
+            }
+            assert_eq!(waits.pending_waits(), 0);
+        }));
+
+        // the simulation does not advance to the deadline of the dropped timer
+        assert_eq!(
+            executor.run_to_quiescence().unwrap(),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn test_zero_duration_timer() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+
+        executor.spawn(Task::new(example_task(
+            reactor.clone(),
+            time.clone(),
+            Duration::ZERO,
+        )));
+        executor.run_ready_tasks();
+
+        assert!(
+            executor.tasks.is_empty(),
+            "timer should have fired on first poll"
+        );
+        assert_eq!(reactor.advance_simulation(), None);
+        assert_eq!(time.now(), start);
+    }
+
+    #[test]
+    fn test_timer_polls() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let time = reactor.get_deterministic_time();
+        let start = time.now();
+
+        executor.spawn(Task::new(example_task(
+            reactor.clone(),
+            time.clone(),
+            Duration::from_secs(5),
+        )));
+        // one timer after the other, each one adding a single poll
+        let (second, waits) = (time.clone(), reactor);
+        executor.spawn(Task::new(async move {
+            for seconds in [1, 2] {
+                let duration = Duration::from_secs(seconds);
+                DeterministicTimer::wait_with_reactor(second.clone(), waits.clone(), duration)
+                    .await;
+            }
+        }));
+        executor.run();
+
+        assert_eq!(executor.poll_count(TaskId::new(0)), 2);
+        assert_eq!(executor.poll_count(TaskId::new(1)), 3);
+        assert_eq!(time.now().duration_since(start), Duration::from_secs(5));
+    }
 }
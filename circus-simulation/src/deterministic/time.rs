@@ -1,13 +1,131 @@
 //! Deterministic time
 use parking_lot::Mutex;
+use std::ops::{Add, Sub};
 use std::sync::Arc;
 use std::time;
 
+/// the wall-clock time of the start of a simulation, since the UNIX epoch: 2020-01-01T00:00:00Z
+const SYSTEM_START: time::Duration = time::Duration::from_secs(1_577_836_800);
+
 #[derive(Debug, Clone)]
 /// A mock source of time, allowing for deterministic control of the progress
 /// of time.
 pub struct DeterministicTime {
     inner: Arc<Mutex<Inner>>,
+    skew: ClockSkew,
+    // rate, in parts per million, at which this view gains (or loses, when negative) time
+    drift: i64,
+}
+
+/// Offset between a clock and the simulated timeline, used to simulate nodes disagreeing on time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockSkew {
+    /// the clock is ahead of the simulated timeline
+    Ahead(time::Duration),
+    /// the clock is behind the simulated timeline
+    Behind(time::Duration),
+}
+
+impl Default for ClockSkew {
+    fn default() -> Self {
+        ClockSkew::Ahead(time::Duration::from_millis(0))
+    }
+}
+
+/// An instant on the simulated timeline, measured from the start of the timeline instead of
+/// from a real [`time::Instant`]. Unlike the instants returned by [`DeterministicTime::now`],
+/// the instants of timelines created at different real times can be compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SimInstant(time::Duration);
+
+impl SimInstant {
+    /// returns the simulated time elapsed since the start of the timeline
+    pub fn since_start(&self) -> time::Duration {
+        self.0
+    }
+
+    /// returns the simulated time elapsed since `earlier`, or zero if `earlier` is later
+    pub fn duration_since(&self, earlier: SimInstant) -> time::Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+}
+
+impl Add<time::Duration> for SimInstant {
+    type Output = SimInstant;
+
+    /// saturates at the latest representable instant
+    fn add(self, duration: time::Duration) -> SimInstant {
+        SimInstant(saturating_add(
+            self.0,
+            duration,
+            time::Duration::checked_add,
+        ))
+    }
+}
+
+impl Sub<time::Duration> for SimInstant {
+    type Output = SimInstant;
+
+    /// saturates at the start of the timeline
+    fn sub(self, duration: time::Duration) -> SimInstant {
+        SimInstant(self.0.saturating_sub(duration))
+    }
+}
+
+impl Sub<SimInstant> for SimInstant {
+    type Output = time::Duration;
+
+    fn sub(self, earlier: SimInstant) -> time::Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// Adds `duration` to `value` with `checked_add`. When it overflows, a warning is logged and the
+/// largest steps that still fit are added instead, so the result saturates at the latest
+/// representable value.
+pub(crate) fn saturating_add<T: Copy>(
+    value: T,
+    duration: time::Duration,
+    checked_add: impl Fn(T, time::Duration) -> Option<T>,
+) -> T {
+    if let Some(sum) = checked_add(value, duration) {
+        return sum;
+    }
+    tracing::warn!("adding {:?} is overflowing the time, saturating", duration);
+    saturate(value, duration, checked_add)
+}
+
+/// Subtracts `duration` from `value` with `checked_sub`, like [`saturating_add`]: the result
+/// saturates at the earliest representable value.
+pub(crate) fn saturating_sub<T: Copy>(
+    value: T,
+    duration: time::Duration,
+    checked_sub: impl Fn(T, time::Duration) -> Option<T>,
+) -> T {
+    if let Some(difference) = checked_sub(value, duration) {
+        return difference;
+    }
+    tracing::warn!(
+        "subtracting {:?} is overflowing the time, saturating",
+        duration
+    );
+    saturate(value, duration, checked_sub)
+}
+
+/// applies the largest steps of `duration` that still fit
+fn saturate<T: Copy>(
+    value: T,
+    duration: time::Duration,
+    checked: impl Fn(T, time::Duration) -> Option<T>,
+) -> T {
+    let (mut value, mut step) = (value, duration);
+    while !step.is_zero() {
+        match checked(value, step) {
+            Some(next) => value = next,
+            None => step /= 2,
+        }
+    }
+    value
 }
 
 #[derive(Debug)]
@@ -45,18 +163,101 @@ impl DeterministicTime {
     pub fn new() -> Self {
         Self {
             inner: Arc::from(Mutex::new(Inner::default())),
+            skew: ClockSkew::default(),
+            drift: 0,
+        }
+    }
+
+    /// returns a view of the same timeline, shifted by `skew`.
+    /// Both views are advancing together.
+    pub fn with_skew(&self, skew: ClockSkew) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            skew,
+            drift: self.drift,
         }
     }
 
-    /// advance of some duration
+    /// returns the skew applied on this view of the timeline
+    pub fn skew(&self) -> ClockSkew {
+        self.skew
+    }
+
+    /// returns a view of the same timeline, whose clock gains `ppm` microseconds per second
+    /// advanced on the timeline, or loses them when `ppm` is negative. The drift applies to the
+    /// whole time advanced since the start of the timeline, before the skew.
+    pub fn with_drift(&self, ppm: i64) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            skew: self.skew,
+            drift: ppm,
+        }
+    }
+
+    /// returns the drift, in parts per million, applied on this view of the timeline
+    pub fn drift(&self) -> i64 {
+        self.drift
+    }
+
+    /// applies the drift on the time advanced on the timeline
+    fn drifted(&self, advance: time::Duration) -> time::Duration {
+        if self.drift == 0 {
+            return advance;
+        }
+        let offset = advance.as_nanos() * self.drift.unsigned_abs() as u128 / 1_000_000;
+        let offset = time::Duration::from_nanos(u64::try_from(offset).unwrap_or(u64::MAX));
+        if self.drift > 0 {
+            advance.saturating_add(offset)
+        } else {
+            advance.saturating_sub(offset)
+        }
+    }
+
+    /// advance of some duration. The time saturates at the latest representable instant,
+    /// instead of overflowing.
     pub fn advance(&self, duration: time::Duration) {
-        self.inner.lock().advance += duration;
+        let mut lock = self.inner.lock();
+        let base = lock.base;
+        lock.advance = saturating_add(lock.advance, duration, |advance, step| {
+            advance
+                .checked_add(step)
+                .filter(|advance| base.checked_add(*advance).is_some())
+        });
     }
 
-    /// return base+advance time
+    /// return base+advance time, drifted and shifted by the skew.
+    /// A skew behind the timeline saturates at the earliest representable instant.
     pub fn now(&self) -> time::Instant {
         let lock = self.inner.lock();
-        lock.base + lock.advance
+        let now = lock
+            .base
+            .checked_add(self.drifted(lock.advance))
+            .unwrap_or(lock.base + lock.advance);
+        match self.skew {
+            ClockSkew::Ahead(skew) => saturating_add(now, skew, |now, skew| now.checked_add(skew)),
+            ClockSkew::Behind(skew) => saturating_sub(now, skew, |now, skew| now.checked_sub(skew)),
+        }
+    }
+
+    /// return the advance time as a [`SimInstant`], drifted and shifted by the skew.
+    /// A skew behind the timeline saturates at its start.
+    pub fn sim_now(&self) -> SimInstant {
+        let advance = self.drifted(self.inner.lock().advance);
+        match self.skew {
+            ClockSkew::Ahead(skew) => {
+                SimInstant(saturating_add(advance, skew, time::Duration::checked_add))
+            }
+            ClockSkew::Behind(skew) => SimInstant(advance.saturating_sub(skew)),
+        }
+    }
+
+    /// returns the wall-clock time of this view: the simulation starts on 2020-01-01 at
+    /// midnight UTC, whatever the real time is, and advances like [`DeterministicTime::sim_now`]
+    pub fn system_now(&self) -> time::SystemTime {
+        let elapsed = self.sim_now().since_start();
+        saturating_add(time::UNIX_EPOCH + SYSTEM_START, elapsed, |now, elapsed| {
+            now.checked_add(elapsed)
+        })
     }
 
     /// reset time
@@ -68,7 +269,11 @@ impl DeterministicTime {
 }
 #[cfg(test)]
 mod tests {
-    use crate::deterministic::time::DeterministicTime;
+    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::reactor::DeterministicReactor;
+    use crate::deterministic::runtime::task::Task;
+    use crate::deterministic::runtime::timer::DeterministicTimer;
+    use crate::deterministic::time::{ClockSkew, DeterministicTime, SimInstant};
     use std::ops::Add;
     use std::time;
     use std::time::Duration;
@@ -89,4 +294,125 @@ mod tests {
         assert!(!time.inner.lock().base.eq(&now));
         dbg!(&time);
     }
+
+    #[test]
+    fn advance_overflow() {
+        let reactor = DeterministicReactor::default();
+        let time = reactor.get_deterministic_time();
+        time.advance(Duration::from_secs(30 * 365 * 24 * 3600));
+        let thirty_years = time.now();
+
+        time.advance(Duration::MAX);
+        let saturated = time.now();
+        assert!(saturated > thirty_years);
+
+        time.advance(Duration::MAX);
+        time.advance(Duration::from_secs(1));
+        assert_eq!(time.now(), saturated);
+
+        // the clocks ahead of the timeline saturate as well
+        let ahead = time.with_skew(ClockSkew::Ahead(Duration::from_secs(1)));
+        assert_eq!(ahead.now(), saturated);
+        let far_ahead = time.with_skew(ClockSkew::Ahead(Duration::MAX));
+        assert_eq!(far_ahead.sim_now().since_start(), Duration::MAX);
+        assert!(far_ahead.system_now() >= time.system_now());
+
+        // and so do the timers registered after the saturation
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let (timer_time, timer_reactor) = (time.clone(), reactor);
+        executor.spawn(Task::new(async move {
+            for duration in [Duration::from_secs(1), Duration::MAX] {
+                let (time, reactor) = (timer_time.clone(), timer_reactor.clone());
+                DeterministicTimer::wait_with_reactor(time, reactor, duration).await;
+            }
+        }));
+        executor.run();
+        assert_eq!(time.now(), saturated);
+    }
+
+    #[test]
+    fn drifting_time() {
+        let time = DeterministicTime::default();
+        let gaining = time.with_drift(50);
+        let losing = time
+            .with_skew(ClockSkew::Ahead(Duration::from_secs(10)))
+            .with_drift(-50);
+        assert_eq!(losing.drift(), -50);
+        assert_eq!(losing.skew(), ClockSkew::Ahead(Duration::from_secs(10)));
+
+        // a day is drifting by 4.32s at 50ppm
+        time.advance(Duration::from_secs(24 * 60 * 60));
+        let drift = Duration::from_millis(4320);
+        assert_eq!(gaining.now().duration_since(time.now()), drift);
+        assert_eq!(
+            losing.now().duration_since(time.now()),
+            Duration::from_secs(10) - drift
+        );
+        assert_eq!(
+            gaining.sim_now().since_start(),
+            Duration::from_secs(24 * 60 * 60) + drift
+        );
+
+        // the divergence is proportional to the time advanced
+        time.advance(Duration::from_secs(24 * 60 * 60));
+        assert_eq!(gaining.now().duration_since(time.now()), drift * 2);
+    }
+
+    #[test]
+    fn skewed_time() {
+        let time = DeterministicTime::default();
+        let ahead = time.with_skew(ClockSkew::Ahead(Duration::from_millis(250)));
+        let behind = time.with_skew(ClockSkew::Behind(Duration::from_millis(100)));
+        assert_eq!(behind.skew(), ClockSkew::Behind(Duration::from_millis(100)));
+
+        for _ in 0..100 {
+            time.advance(Duration::from_millis(42));
+            assert_eq!(
+                ahead.now().duration_since(time.now()),
+                Duration::from_millis(250)
+            );
+            assert_eq!(
+                time.now().duration_since(behind.now()),
+                Duration::from_millis(100)
+            );
+            assert_eq!(
+                ahead.now().duration_since(behind.now()),
+                Duration::from_millis(350)
+            );
+        }
+
+        // a clock far behind the timeline saturates instead of panicking
+        let far_behind = time.with_skew(ClockSkew::Behind(Duration::MAX));
+        let earliest = far_behind.now();
+        assert!(earliest <= time.now());
+        time.advance(Duration::from_secs(1));
+        assert!(far_behind.now() >= earliest);
+        assert_eq!(far_behind.sim_now().since_start(), Duration::ZERO);
+    }
+
+    #[test]
+    fn sim_instant() {
+        let first = DeterministicTime::new();
+        std::thread::sleep(Duration::from_millis(1));
+        let second = DeterministicTime::new();
+        let skewed = first.with_skew(ClockSkew::Ahead(Duration::from_secs(1)));
+
+        first.advance(Duration::from_secs(5));
+        second.advance(Duration::from_secs(5));
+        // the real instants differ, as the bases differ
+        assert_ne!(first.now(), second.now());
+        assert_eq!(first.sim_now(), second.sim_now());
+        assert_eq!(first.sim_now().since_start(), Duration::from_secs(5));
+
+        second.advance(Duration::from_secs(1));
+        assert!(first.sim_now() < second.sim_now());
+        assert_eq!(skewed.sim_now(), second.sim_now());
+        assert_eq!(second.sim_now() - first.sim_now(), Duration::from_secs(1));
+        assert_eq!(first.sim_now() - second.sim_now(), Duration::ZERO);
+        assert_eq!(first.sim_now() + Duration::from_secs(1), second.sim_now());
+        assert_eq!(
+            first.sim_now() - Duration::from_secs(10),
+            SimInstant::default()
+        );
+    }
 }
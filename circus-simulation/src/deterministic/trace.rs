@@ -0,0 +1,149 @@
+//! Trace module
+use std::fmt;
+use std::str::FromStr;
+
+/// Recording of the random values drawn and buggify decisions taken during a simulation.
+/// Replaying it feeds back the recorded values instead of drawing them, so a failure can be
+/// reproduced even after the code changed. It is serialized as text with
+/// [`fmt::Display`], and deserialized with [`FromStr`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    /// the random values drawn, in order
+    pub draws: Vec<u64>,
+    /// the buggify decisions taken, in order
+    pub decisions: Vec<bool>,
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "draws")?;
+        for draw in &self.draws {
+            write!(f, " {}", draw)?;
+        }
+        write!(f, "\ndecisions")?;
+        for decision in &self.decisions {
+            write!(f, " {}", *decision as u8)?;
+        }
+        writeln!(f)
+    }
+}
+
+/// Error returned when a replay did not follow its [`Trace`], usually because the code changed
+/// since the recording. The divergence is detected from the number of random values drawn
+/// and of buggify decisions taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayDivergence {
+    /// the simulation did not draw as many random values as recorded
+    Draws {
+        /// index of the first draw that does not match the recording
+        step: usize,
+        /// number of values recorded
+        recorded: usize,
+        /// number of values drawn while replaying
+        requested: usize,
+    },
+    /// the simulation did not take as many buggify decisions as recorded
+    Decisions {
+        /// index of the first decision that does not match the recording
+        step: usize,
+        /// number of decisions recorded
+        recorded: usize,
+        /// number of decisions taken while replaying
+        requested: usize,
+    },
+}
+
+impl fmt::Display for ReplayDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (what, step, recorded, requested) = match self {
+            ReplayDivergence::Draws {
+                step,
+                recorded,
+                requested,
+            } => ("draw", step, recorded, requested),
+            ReplayDivergence::Decisions {
+                step,
+                recorded,
+                requested,
+            } => ("decision", step, recorded, requested),
+        };
+        write!(
+            f,
+            "replay diverged at {} {}: {} recorded, {} requested",
+            what, step, recorded, requested
+        )
+    }
+}
+
+impl std::error::Error for ReplayDivergence {}
+
+/// Error returned when a [`Trace`] cannot be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTraceError(String);
+
+impl fmt::Display for ParseTraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "could not parse trace: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseTraceError {}
+
+impl FromStr for Trace {
+    type Err = ParseTraceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut trace = Trace::default();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("draws") => {
+                    for field in fields {
+                        let draw = field
+                            .parse()
+                            .map_err(|_| ParseTraceError(format!("invalid draw '{}'", field)))?;
+                        trace.draws.push(draw);
+                    }
+                }
+                Some("decisions") => {
+                    for field in fields {
+                        let decision = match field {
+                            "0" => false,
+                            "1" => true,
+                            _ => {
+                                return Err(ParseTraceError(format!(
+                                    "invalid decision '{}'",
+                                    field
+                                )))
+                            }
+                        };
+                        trace.decisions.push(decision);
+                    }
+                }
+                _ => return Err(ParseTraceError(format!("unexpected line '{}'", line))),
+            }
+        }
+        Ok(trace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deterministic::trace::Trace;
+
+    #[test]
+    fn test_trace_serialization() {
+        let trace = Trace {
+            draws: vec![42, u64::MAX, 0],
+            decisions: vec![false, true],
+        };
+        let text = trace.to_string();
+        assert_eq!(text, "draws 42 18446744073709551615 0\ndecisions 0 1\n");
+        assert_eq!(text.parse::<Trace>(), Ok(trace));
+        assert_eq!("".parse::<Trace>(), Ok(Trace::default()));
+
+        assert!("draws 1 two".parse::<Trace>().is_err());
+        assert!("decisions 2".parse::<Trace>().is_err());
+        assert!("seeds 1".parse::<Trace>().is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! Error module
+use std::{error, fmt, io};
+
+/// Errors raised by the simulation
+#[derive(Debug)]
+pub enum SimError {
+    /// an I/O error, simulated or not
+    Io(io::Error),
+    /// tasks are still pending, but nothing can make them progress
+    Deadlock,
+    /// the simulation exceeded its budget
+    BudgetExceeded,
+    /// the remote node cannot be reached
+    Partitioned,
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::Io(error) => write!(f, "I/O error: {}", error),
+            SimError::Deadlock => write!(f, "simulation is deadlocked"),
+            SimError::BudgetExceeded => write!(f, "simulation exceeded its budget"),
+            SimError::Partitioned => write!(f, "node is partitioned"),
+        }
+    }
+}
+
+impl error::Error for SimError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SimError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SimError {
+    fn from(error: io::Error) -> Self {
+        SimError::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::SimError;
+    use std::error::Error;
+    use std::io;
+
+    #[test]
+    fn test_from_io() {
+        let error = SimError::from(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(matches!(&error, SimError::Io(e) if e.kind() == io::ErrorKind::PermissionDenied));
+        assert!(error.source().is_some());
+        assert!(SimError::Deadlock.source().is_none());
+    }
+}
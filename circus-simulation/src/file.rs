@@ -1,14 +1,74 @@
 //! File module
+#[cfg(feature = "simulation")]
 use crate::deterministic::fs::file::SimulatedFile;
+#[cfg(feature = "async-std")]
+use crate::production::async_std::AsyncStdFile;
+use crate::production::file::ProductionFile;
+#[cfg(feature = "tokio")]
+use crate::production::tokio::TokioFile;
+use async_trait::async_trait;
 use enum_dispatch::enum_dispatch;
+use std::io;
+use std::io::SeekFrom;
 
 /// File trait
+#[async_trait]
 #[enum_dispatch(File)]
-pub trait FileTrait {}
+pub trait FileTrait {
+    /// read some bytes into `buf`, returning how many bytes were read
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// write some bytes from `buf`, returning how many bytes were written
+    async fn write(&mut self, buf: &[u8]) -> io::Result<usize>;
+
+    /// flush the written bytes to the disk
+    async fn sync_all(&mut self) -> io::Result<()>;
+
+    /// Move the position of the next read or write, returning the new position from the start
+    /// of the file, like [`std::io::Seek::seek`]. Seeking past the end is allowed, seeking
+    /// before the start fails with [`io::ErrorKind::InvalidInput`].
+    async fn seek(&mut self, position: SeekFrom) -> io::Result<u64>;
+
+    /// returns the identity of the file, like an inode number: two handles with the same
+    /// identity are on the same file, even when opened through different paths
+    fn file_id(&self) -> io::Result<u64>;
+}
+
+#[async_trait]
+impl<F: FileTrait + Send + ?Sized> FileTrait for Box<F> {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read(buf).await
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (**self).write(buf).await
+    }
+
+    async fn sync_all(&mut self) -> io::Result<()> {
+        (**self).sync_all().await
+    }
+
+    async fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        (**self).seek(position).await
+    }
+
+    fn file_id(&self) -> io::Result<u64> {
+        (**self).file_id()
+    }
+}
 
 /// Enum for the File trait
 #[enum_dispatch]
 pub enum File {
     /// A simulated file
+    #[cfg(feature = "simulation")]
     SimulatedFile,
+    /// A file backed by the OS
+    ProductionFile,
+    /// A file backed by Tokio, boxed as it is much larger than the other variants
+    #[cfg(feature = "tokio")]
+    TokioFile(Box<TokioFile>),
+    /// A file backed by async-std, boxed as it is much larger than the other variants
+    #[cfg(feature = "async-std")]
+    AsyncStdFile(Box<AsyncStdFile>),
 }
@@ -5,10 +5,120 @@
 //! Highly experimental for now, but the end-goal is to provide some of the helpers that the [FoundationDB's developers](https://www.foundationdb.org/) have:
 //! * a [simulator](https://www.youtube.com/watch?v=4fFDFbi3toc)
 //! * a [rpc framework](https://forums.foundationdb.org/t/why-was-flow-developed/1711/2) built using the simulator
+//!
 //! It will be compatible with both `async-std` and `Tokio`, allowing you to use Circus during development, then switch to your favorite runtime in production.
 //!
 //! Examples can be found in the [examples folder](https://github.com/PierreZ/circus/tree/main/simulation/examples).
+//!
+//! ## Features
+//! * `simulation` (enabled by default): provides the simulated platform. When disabled, only the
+//!   production platform is available, and [`buggify!`] is always evaluating to `false`.
+//!   It enables the `buggify` feature of `circus_buggify`.
+//! * `tokio`: provides [`production::tokio::TokioPlatform`], backed by Tokio's file I/O and timers.
+//! * `async-std`: provides [`production::async_std::AsyncStdPlatform`], backed by async-std's
+//!   file I/O and timers. It can be enabled together with `tokio`.
 
+#[cfg(feature = "simulation")]
+pub mod assertions;
 pub mod deterministic;
+pub mod error;
 pub mod file;
+pub mod panic_hook;
 pub mod platform;
+pub mod prelude;
+pub mod production;
+pub mod retry;
+#[cfg(feature = "simulation")]
+pub mod run;
+#[cfg(feature = "simulation")]
+pub mod search;
+
+#[cfg(feature = "simulation")]
+pub use assertions::assert_deterministic;
+pub use panic_hook::install_seed_panic_hook;
+#[cfg(feature = "simulation")]
+pub use run::run;
+#[cfg(feature = "simulation")]
+pub use search::run_search;
+
+#[doc(hidden)]
+pub use circus_buggify;
+
+/// Evaluates to `true` when the global buggifier decides to inject a fault at this call site,
+/// see [`circus_buggify::buggify`]. A probability can be given, like `buggify!(0.5)`,
+/// or as a ratio for rare faults, like `buggify!(1 in 1000)`.
+///
+/// Without the `simulation` feature, it always evaluates to `false` so it can be left in production code.
+#[cfg(feature = "simulation")]
+#[macro_export]
+macro_rules! buggify {
+    (@ratio [$($numerator:tt)+] in $denominator:expr) => {
+        $crate::circus_buggify::buggify_with_ratio(($($numerator)+), $denominator)
+    };
+    (@ratio [$($numerator:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::buggify!(@ratio [$($numerator)* $next] $($rest)*)
+    };
+    () => {
+        $crate::circus_buggify::buggify()
+    };
+    ($probability:expr) => {
+        $crate::circus_buggify::buggify_with_prob($probability)
+    };
+    ($($ratio:tt)+) => {
+        $crate::buggify!(@ratio [] $($ratio)+)
+    };
+}
+
+/// Evaluates to `true` when the global buggifier decides to inject a fault at this call site,
+/// see [`circus_buggify::buggify`]. A probability can be given, like `buggify!(0.5)`,
+/// or as a ratio for rare faults, like `buggify!(1 in 1000)`.
+///
+/// Without the `simulation` feature, it always evaluates to `false` so it can be left in production code.
+#[cfg(not(feature = "simulation"))]
+#[macro_export]
+macro_rules! buggify {
+    () => {
+        false
+    };
+    ($probability:expr) => {
+        false
+    };
+    ($($ratio:tt)+) => {
+        false
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use circus_buggify::enable_buggify;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[test]
+    #[cfg(feature = "simulation")]
+    fn test_buggify_macro() {
+        enable_buggify(SmallRng::seed_from_u64(42));
+        // a site only fires once per run
+        let fired: Vec<bool> = (0..3).map(|_| buggify!(1.0)).collect();
+        assert_eq!(fired, vec![true, false, false]);
+    }
+
+    #[test]
+    #[cfg(feature = "simulation")]
+    fn test_buggify_macro_ratio() {
+        enable_buggify(SmallRng::seed_from_u64(42));
+        let denominator = 4;
+        assert!(buggify!(1 in 1));
+        assert!(buggify!(denominator - 3 in denominator / 4));
+        assert!(!buggify!(0 in 1));
+    }
+
+    #[test]
+    #[cfg(not(feature = "simulation"))]
+    fn test_buggify_macro() {
+        enable_buggify(SmallRng::seed_from_u64(42));
+        assert!(!buggify!(1.0));
+        assert!(!buggify!());
+        assert!(!buggify!(1 in 1));
+    }
+}
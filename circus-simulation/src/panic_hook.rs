@@ -0,0 +1,89 @@
+//! Panic hook module
+use std::cell::Cell;
+use std::panic;
+use std::sync::Once;
+
+thread_local! {
+    /// seed of the run in progress on this thread
+    static SEED: Cell<Option<u64>> = Cell::new(None);
+}
+
+static INSTALL: Once = Once::new();
+
+/// Installs a panic hook reporting the seed of the run in progress, as `[circus seed=N]`, after
+/// any panic message on the current thread. The previous hook is still called, so the usual
+/// message and backtrace are printed first. The hook is installed once for the process, and
+/// the seed only applies to the current thread until the returned guard is dropped.
+pub fn install_seed_panic_hook(seed: u64) -> SeedPanicGuard {
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            previous(info);
+            if let Some(seed) = SEED.with(Cell::get) {
+                eprintln!("[circus seed={}]", seed);
+            }
+        }));
+    });
+    let previous = SEED.with(|current| current.replace(Some(seed)));
+    SeedPanicGuard { previous }
+}
+
+/// Guard returned by [`install_seed_panic_hook`], restoring the previous seed on drop
+#[derive(Debug)]
+#[must_use = "the seed is only reported while the guard is alive"]
+pub struct SeedPanicGuard {
+    previous: Option<u64>,
+}
+
+impl Drop for SeedPanicGuard {
+    fn drop(&mut self) {
+        SEED.with(|current| current.set(self.previous));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::panic_hook::install_seed_panic_hook;
+    use std::process::Command;
+
+    const CHILD: &str = "CIRCUS_SEED_PANIC_CHILD";
+
+    /// only panics when spawned by `test_seed_panic_hook`
+    #[test]
+    fn seeded_panic_child() {
+        if std::env::var_os(CHILD).is_some() {
+            let _guard = install_seed_panic_hook(42);
+            panic!("simulation failed");
+        }
+    }
+
+    #[test]
+    fn test_seed_panic_hook() {
+        // the hook writes to stderr, so the panicking run happens in a child process
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args([
+                "panic_hook::tests::seeded_panic_child",
+                "--exact",
+                "--nocapture",
+            ])
+            .env(CHILD, "1")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("simulation failed"), "{}", stderr);
+        assert!(stderr.contains("[circus seed=42]"), "{}", stderr);
+    }
+
+    #[test]
+    fn test_seed_panic_guard() {
+        let outer = install_seed_panic_hook(1);
+        {
+            let _inner = install_seed_panic_hook(2);
+            assert_eq!(super::SEED.with(|seed| seed.get()), Some(2));
+        }
+        assert_eq!(super::SEED.with(|seed| seed.get()), Some(1));
+        drop(outer);
+        assert_eq!(super::SEED.with(|seed| seed.get()), None);
+    }
+}
@@ -1,9 +1,16 @@
 //! Platform module
+#[cfg(feature = "simulation")]
 use crate::deterministic::platform::SimulationPlatform;
-use crate::file::File;
+use crate::file::{File, FileTrait};
+#[cfg(feature = "async-std")]
+use crate::production::async_std::AsyncStdPlatform;
+use crate::production::platform::ProductionPlatform;
+#[cfg(feature = "tokio")]
+use crate::production::tokio::TokioPlatform;
 use async_trait::async_trait;
 use enum_dispatch::enum_dispatch;
-use std::path::Path;
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::{io, time};
 
 /// Main trait for platform
@@ -13,13 +20,71 @@ pub trait Platform {
     /// open a file
     async fn open(&mut self, path: &Path) -> io::Result<File>;
 
+    /// open a file, failing with [`io::ErrorKind::TimedOut`] if it takes longer than `timeout`
+    async fn open_timeout(&mut self, path: &Path, timeout: time::Duration) -> io::Result<File>;
+
+    /// create a file for reading and writing, truncating it if it exists
+    async fn create(&mut self, path: &Path) -> io::Result<File>;
+
+    /// Sync the entries of the directory at `path`, so the files created in it so far
+    /// survive a crash. Syncing a file is not making its directory entry durable.
+    async fn fsync_dir(&mut self, path: &Path) -> io::Result<()>;
+
+    /// read the entire contents of a file into a string
+    async fn read_to_string(&mut self, path: &Path) -> io::Result<String>;
+
+    /// write the whole `buf` to `file`, calling [`FileTrait::write`] until every byte is written,
+    /// like [`std::io::Write::write_all`]. Fails with [`io::ErrorKind::WriteZero`] if a write
+    /// returns 0.
+    async fn write_all(&mut self, file: &mut File, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match file.write(buf).await {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero)),
+                Ok(written) => buf = &buf[written..],
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    /// list the entries of a directory
+    async fn read_dir(&mut self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// sleep for the given duration
+    async fn sleep(&self, duration: time::Duration);
+
     /// return the current time
     fn now(&self) -> time::Instant;
+
+    /// Awaits `future`, returning its output and the time elapsed according to the platform.
+    /// In simulation, durations must be measured with the platform instead of
+    /// [`std::time::Instant::now`], which is measuring the real time.
+    async fn measure_async<F>(&self, future: F) -> (F::Output, time::Duration)
+    where
+        F: Future + Send,
+    {
+        let start = self.now();
+        let output = future.await;
+        (output, self.now().saturating_duration_since(start))
+    }
+
+    /// return the current wall-clock time, to produce timestamps
+    fn system_now(&self) -> time::SystemTime;
 }
 
 /// Enum of the available platform providers
 #[enum_dispatch]
 pub enum PlatformProvider {
     /// Simulated platform
+    #[cfg(feature = "simulation")]
     SimulationPlatform,
+    /// Production platform
+    ProductionPlatform,
+    /// Production platform backed by Tokio
+    #[cfg(feature = "tokio")]
+    TokioPlatform,
+    /// Production platform backed by async-std
+    #[cfg(feature = "async-std")]
+    AsyncStdPlatform,
 }
@@ -0,0 +1,34 @@
+//! Re-exports the types needed to write a simulation.
+//!
+//! ```rust
+//! # // the simulated platform needs the `simulation` feature
+//! # #[cfg(feature = "simulation")]
+//! # {
+//! use circus_simulation::prelude::*;
+//! use std::time::Duration;
+//!
+//! let reactor = DeterministicReactor::default();
+//! let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+//! let platform: PlatformProvider = SimulationPlatform::new(42, reactor).into();
+//!
+//! executor.spawn(Task::new(async move {
+//!     let start = platform.now();
+//!     platform.sleep(Duration::from_secs(60)).await;
+//!     assert_eq!(platform.now().duration_since(start), Duration::from_secs(60));
+//! }));
+//! executor.run();
+//! # }
+//! ```
+pub use crate::buggify;
+#[cfg(feature = "simulation")]
+pub use crate::deterministic::platform::SimulationPlatform;
+pub use crate::deterministic::random::DeterministicRandom;
+pub use crate::deterministic::runtime::executor::{DeterministicExecutor, Spawner};
+pub use crate::deterministic::runtime::reactor::DeterministicReactor;
+pub use crate::deterministic::runtime::task::Task;
+pub use crate::error::SimError;
+pub use crate::file::{File, FileTrait};
+pub use crate::platform::{Platform, PlatformProvider};
+#[cfg(feature = "simulation")]
+pub use crate::{assert_deterministic, run, run_search};
+pub use circus_buggify::BuggifyMode;
@@ -0,0 +1,150 @@
+//! Production platform backed by async-std
+use crate::file::{File, FileTrait};
+use crate::platform::Platform;
+use crate::production::file::file_id;
+use ::async_std::io::{ReadExt, SeekExt, WriteExt};
+use ::async_std::stream::StreamExt;
+use async_trait::async_trait;
+use std::io;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Production implementation of a file, using async-std's file I/O.
+pub struct AsyncStdFile {
+    file: ::async_std::fs::File,
+    // taken when the file is opened, as the identity of a file never changes
+    metadata: std::fs::Metadata,
+}
+
+impl AsyncStdFile {
+    /// creates an `AsyncStdFile`
+    pub async fn new(file: ::async_std::fs::File) -> io::Result<Self> {
+        let metadata = file.metadata().await?;
+        Ok(AsyncStdFile { file, metadata })
+    }
+}
+
+#[async_trait]
+impl FileTrait for AsyncStdFile {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf).await
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf).await
+    }
+
+    async fn sync_all(&mut self) -> io::Result<()> {
+        // async-std is buffering writes until the file is flushed
+        self.file.flush().await?;
+        self.file.sync_all().await
+    }
+
+    async fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.file.seek(position).await
+    }
+
+    fn file_id(&self) -> io::Result<u64> {
+        file_id(&self.metadata)
+    }
+}
+
+/// Production version of the platform, backed by async-std's file I/O and timers.
+#[derive(Clone, Debug, Default)]
+pub struct AsyncStdPlatform {}
+
+impl AsyncStdPlatform {
+    /// creates a new `AsyncStdPlatform`
+    pub fn new() -> Self {
+        AsyncStdPlatform {}
+    }
+}
+
+#[async_trait]
+impl Platform for AsyncStdPlatform {
+    async fn open(&mut self, path: &Path) -> io::Result<File> {
+        let file = ::async_std::fs::File::open(path).await?;
+        AsyncStdFile::new(file)
+            .await
+            .map(|file| File::from(Box::new(file)))
+    }
+
+    async fn open_timeout(&mut self, path: &Path, timeout: Duration) -> io::Result<File> {
+        ::async_std::future::timeout(timeout, self.open(path))
+            .await
+            .unwrap_or_else(|_| Err(io::Error::from(io::ErrorKind::TimedOut)))
+    }
+
+    async fn create(&mut self, path: &Path) -> io::Result<File> {
+        let file = ::async_std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .await?;
+        AsyncStdFile::new(file)
+            .await
+            .map(|file| File::from(Box::new(file)))
+    }
+
+    async fn fsync_dir(&mut self, path: &Path) -> io::Result<()> {
+        ::async_std::fs::File::open(path).await?.sync_all().await
+    }
+
+    async fn read_to_string(&mut self, path: &Path) -> io::Result<String> {
+        ::async_std::fs::read_to_string(path).await
+    }
+
+    async fn read_dir(&mut self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries = ::async_std::fs::read_dir(path).await?;
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.next().await {
+            paths.push(entry?.path().into());
+        }
+        Ok(paths)
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        ::async_std::task::sleep(duration).await;
+    }
+
+    fn system_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::FileTrait;
+    use crate::platform::Platform;
+    use crate::production::async_std::AsyncStdPlatform;
+    use std::time::Duration;
+
+    #[test]
+    fn test_async_std_platform() {
+        let path = std::env::temp_dir().join(format!("circus-async_std-{}", std::process::id()));
+        ::async_std::task::block_on(async {
+            let mut platform = AsyncStdPlatform::new();
+            let mut file = platform.create(&path).await.expect("could not create file");
+            assert_eq!(file.write(b"hello").await.unwrap(), 5);
+            file.sync_all().await.unwrap();
+
+            let mut opened = platform.open(&path).await.expect("could not open file");
+            assert_eq!(opened.file_id().unwrap(), file.file_id().unwrap());
+            let mut buf = [0; 16];
+            assert_eq!(opened.read(&mut buf).await.unwrap(), 5);
+            assert_eq!(&buf[..5], b"hello");
+
+            let start = platform.now();
+            platform.sleep(Duration::from_millis(10)).await;
+            assert!(platform.now().duration_since(start) >= Duration::from_millis(10));
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,56 @@
+//! Production file module
+use crate::file::FileTrait;
+use async_trait::async_trait;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Production implementation of a file, backed by the OS.
+pub struct ProductionFile {
+    file: std::fs::File,
+}
+
+impl ProductionFile {
+    /// creates a `ProductionFile`
+    pub fn new(file: std::fs::File) -> Self {
+        ProductionFile { file }
+    }
+}
+
+#[async_trait]
+impl FileTrait for ProductionFile {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    async fn sync_all(&mut self) -> io::Result<()> {
+        self.file.sync_all()
+    }
+
+    async fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.file.seek(position)
+    }
+
+    fn file_id(&self) -> io::Result<u64> {
+        file_id(&self.file.metadata()?)
+    }
+}
+
+/// returns the identity of a file from its metadata, see [`FileTrait::file_id`]
+#[cfg(unix)]
+pub(crate) fn file_id(metadata: &std::fs::Metadata) -> io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(metadata.ino())
+}
+
+/// returns the identity of a file from its metadata, see [`FileTrait::file_id`]
+#[cfg(not(unix))]
+pub(crate) fn file_id(_metadata: &std::fs::Metadata) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "file identities are only available on unix",
+    ))
+}
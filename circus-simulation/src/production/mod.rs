@@ -0,0 +1,8 @@
+//! Production implementations, talking to the OS
+#[cfg(feature = "async-std")]
+pub mod async_std;
+pub mod file;
+pub mod platform;
+pub(crate) mod timer;
+#[cfg(feature = "tokio")]
+pub mod tokio;
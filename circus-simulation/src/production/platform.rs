@@ -0,0 +1,112 @@
+//! Production platform module
+use crate::file::File;
+use crate::platform::Platform;
+use crate::production::file::ProductionFile;
+use crate::production::timer::ThreadTimer;
+use async_trait::async_trait;
+use futures::future::{select, Either};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Production version of the platform, every call is forwarded to the OS.
+#[derive(Clone, Debug, Default)]
+pub struct ProductionPlatform {}
+
+impl ProductionPlatform {
+    /// creates a new `ProductionPlatform`
+    pub fn new() -> Self {
+        ProductionPlatform {}
+    }
+}
+
+/// creates a file for reading and writing, truncating it if it exists
+pub(crate) fn create_file(path: &Path) -> io::Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+}
+
+#[async_trait]
+impl Platform for ProductionPlatform {
+    async fn open(&mut self, path: &Path) -> io::Result<File> {
+        std::fs::File::open(path).map(|file| ProductionFile::new(file).into())
+    }
+
+    async fn open_timeout(&mut self, path: &Path, timeout: Duration) -> io::Result<File> {
+        let open = self.open(path);
+        let timer = ThreadTimer::wait(timeout);
+        futures::pin_mut!(open, timer);
+        match select(open, timer).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(io::Error::from(io::ErrorKind::TimedOut)),
+        }
+    }
+
+    async fn create(&mut self, path: &Path) -> io::Result<File> {
+        create_file(path).map(|file| ProductionFile::new(file).into())
+    }
+
+    async fn fsync_dir(&mut self, path: &Path) -> io::Result<()> {
+        std::fs::File::open(path)?.sync_all()
+    }
+
+    async fn read_to_string(&mut self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    async fn read_dir(&mut self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        ThreadTimer::wait(duration).await;
+    }
+
+    fn system_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::FileTrait;
+    use crate::platform::Platform;
+    use crate::production::platform::ProductionPlatform;
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_open() {
+        futures::executor::block_on(async {
+            let mut platform = ProductionPlatform::new();
+            let start = platform.now();
+            assert!(platform.open(Path::new("/etc/hosts")).await.is_ok());
+            platform.sleep(Duration::from_millis(10)).await;
+            assert!(platform.now().duration_since(start) >= Duration::from_millis(10));
+        });
+    }
+
+    #[test]
+    fn test_create() {
+        let path = std::env::temp_dir().join(format!("circus-create-{}", std::process::id()));
+        futures::executor::block_on(async {
+            let mut platform = ProductionPlatform::new();
+            let mut file = platform.create(&path).await.expect("could not create file");
+            assert_eq!(file.write(b"hello").await.unwrap(), 5);
+            file.sync_all().await.unwrap();
+            platform.fsync_dir(&std::env::temp_dir()).await.unwrap();
+            assert_eq!(platform.read_to_string(&path).await.unwrap(), "hello");
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+}
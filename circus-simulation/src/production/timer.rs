@@ -0,0 +1,63 @@
+//! Timer module
+
+use parking_lot::Mutex;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A runtime-agnostic timer, using a thread to wake the task once the deadline is reached.
+pub struct ThreadTimer {
+    deadline: Instant,
+    waker: Option<Arc<Mutex<Waker>>>,
+}
+
+impl ThreadTimer {
+    /// Wait for `duration`
+    pub fn wait(duration: Duration) -> ThreadTimer {
+        ThreadTimer {
+            deadline: Instant::now() + duration,
+            waker: None,
+        }
+    }
+}
+
+impl Future for ThreadTimer {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        match &self.waker {
+            // the task may have moved since the last poll
+            Some(waker) => *waker.lock() = cx.waker().clone(),
+            None => {
+                let waker = Arc::new(Mutex::new(cx.waker().clone()));
+                let deadline = self.deadline;
+                let thread_waker = waker.clone();
+                thread::spawn(move || {
+                    thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                    thread_waker.lock().wake_by_ref();
+                });
+                self.waker = Some(waker);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::production::timer::ThreadTimer;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_timer() {
+        let start = Instant::now();
+        futures::executor::block_on(ThreadTimer::wait(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,202 @@
+//! Retry module
+use crate::deterministic::random::DeterministicRandom;
+use crate::platform::Platform;
+use std::future::Future;
+use std::time::Duration;
+
+/// Delay between two attempts of [`retry`]
+pub const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Describes how [`backoff_retry`] is spacing its attempts. The delay between two attempts
+/// starts at `initial_delay` and doubles after each failure, up to `max_delay`.
+#[derive(Debug, Clone)]
+pub struct BackoffPolicy {
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_attempts: usize,
+    jitter: Option<DeterministicRandom>,
+}
+
+impl BackoffPolicy {
+    /// creates a new `BackoffPolicy`, `max_attempts` is including the first attempt
+    pub fn new(initial_delay: Duration, max_delay: Duration, max_attempts: usize) -> Self {
+        BackoffPolicy {
+            initial_delay,
+            max_delay,
+            max_attempts,
+            jitter: None,
+        }
+    }
+
+    /// randomize each delay between half and the whole of its value, using a deterministic source
+    pub fn with_jitter(mut self, random: DeterministicRandom) -> Self {
+        self.jitter = Some(random);
+        self
+    }
+
+    /// returns the delay to wait after the failed attempt `attempt`, starting at 0
+    fn delay(&mut self, attempt: usize) -> Duration {
+        let factor = 1u32.checked_shl(attempt as u32).unwrap_or(u32::MAX);
+        let delay = self
+            .initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        match self.jitter.as_mut() {
+            Some(random) if !delay.is_zero() => random.random_duration(delay / 2..delay),
+            _ => delay,
+        }
+    }
+}
+
+/// Calls `operation` until it succeeds, sleeping on the platform between attempts according
+/// to the `policy`. Returns the first success, or the last error once every attempt failed.
+pub async fn backoff_retry<P, F, Fut, T, E>(
+    platform: &P,
+    mut policy: BackoffPolicy,
+    mut operation: F,
+) -> Result<T, E>
+where
+    P: Platform,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(error) if attempt + 1 >= policy.max_attempts => return Err(error),
+            Err(_) => {
+                let delay = policy.delay(attempt);
+                tracing::debug!("attempt {} failed, retrying in {:?}", attempt, delay);
+                platform.sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Calls `operation` until it succeeds, sleeping [`RETRY_DELAY`] on the platform between
+/// attempts, at most `max_attempts` times. Returns the first success, or the last error once
+/// every attempt failed. See [`backoff_retry`] to space the attempts differently.
+pub async fn retry<P, F, Fut, T, E>(platform: &P, max_attempts: usize, operation: F) -> Result<T, E>
+where
+    P: Platform,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let policy = BackoffPolicy::new(RETRY_DELAY, RETRY_DELAY, max_attempts);
+    backoff_retry(platform, policy, operation).await
+}
+
+#[cfg(all(test, feature = "simulation"))]
+mod tests {
+    use crate::deterministic::platform::SimulationPlatform;
+    use crate::deterministic::random::DeterministicRandom;
+    use crate::deterministic::runtime::executor::DeterministicExecutor;
+    use crate::deterministic::runtime::reactor::DeterministicReactor;
+    use crate::deterministic::runtime::task::Task;
+    use crate::platform::Platform;
+    use crate::retry::{backoff_retry, retry, BackoffPolicy, RETRY_DELAY};
+    use parking_lot::RwLock;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn run_with_policy(policy: BackoffPolicy, failures: usize) -> (Result<usize, usize>, Duration) {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let state = Arc::new(RwLock::new(None));
+
+        let result = state.clone();
+        executor.spawn(Task::new(async move {
+            let platform = SimulationPlatform::new(42, reactor);
+            let start = platform.now();
+            let mut attempts = 0;
+            let outcome = backoff_retry(&platform, policy, || {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt > failures {
+                        Ok(attempt)
+                    } else {
+                        Err(attempt)
+                    }
+                }
+            })
+            .await;
+            *result.write() = Some((outcome, platform.now().duration_since(start)));
+        }));
+        executor.run();
+
+        let outcome = state.write().take();
+        outcome.expect("simulation did not complete")
+    }
+
+    #[test]
+    fn test_backoff_retry() {
+        let policy = BackoffPolicy::new(Duration::from_millis(100), Duration::from_secs(10), 5);
+        let (result, elapsed) = run_with_policy(policy, 2);
+        assert_eq!(result, Ok(3));
+        // 100ms + 200ms
+        assert_eq!(elapsed, Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_backoff_retry_exhausted() {
+        let policy = BackoffPolicy::new(Duration::from_millis(100), Duration::from_millis(250), 4);
+        let (result, elapsed) = run_with_policy(policy, 10);
+        assert_eq!(result, Err(4));
+        // 100ms + 200ms + 250ms
+        assert_eq!(elapsed, Duration::from_millis(550));
+    }
+
+    #[test]
+    fn test_backoff_retry_jitter() {
+        let jittered = |seed| {
+            let policy = BackoffPolicy::new(Duration::from_millis(100), Duration::from_secs(10), 5)
+                .with_jitter(DeterministicRandom::new_with_seed(seed));
+            run_with_policy(policy, 2)
+        };
+
+        let (result, elapsed) = jittered(42);
+        assert_eq!(result, Ok(3));
+        assert!(elapsed >= Duration::from_millis(150) && elapsed < Duration::from_millis(300));
+        assert_eq!(jittered(42), (result, elapsed));
+    }
+
+    #[test]
+    fn test_retry_buggified_open() {
+        let reactor = DeterministicReactor::default();
+        let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+        let state = Arc::new(RwLock::new(None));
+
+        let result = state.clone();
+        executor.spawn(Task::new(async move {
+            // using seed 6, the first open is buggified
+            let platform = SimulationPlatform::new(6, reactor);
+            let start = platform.now();
+            let mut attempts = 0;
+            let opened = retry(&platform, 3, || {
+                attempts += 1;
+                let mut platform = platform.clone();
+                async move { platform.open(Path::new("/etc/hosts")).await }
+            })
+            .await;
+            *result.write() = Some((
+                opened.is_ok(),
+                attempts,
+                platform.now().duration_since(start),
+            ));
+        }));
+        executor.run();
+
+        let (opened, attempts, elapsed) =
+            state.write().take().expect("simulation did not complete");
+        assert!(opened, "the open was not retried");
+        assert_eq!(attempts, 2);
+        // the failed open returns at once, then 100ms of delay and 899ms to open the file
+        assert_eq!(elapsed, RETRY_DELAY + Duration::from_millis(899));
+    }
+}
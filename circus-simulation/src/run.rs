@@ -0,0 +1,81 @@
+//! Run module
+use crate::deterministic::platform::SimulationPlatform;
+use crate::deterministic::runtime::executor::DeterministicExecutor;
+use crate::deterministic::runtime::reactor::DeterministicReactor;
+use crate::deterministic::runtime::task::Task;
+use std::cell::RefCell;
+use std::future::Future;
+use std::rc::Rc;
+
+/// Runs `simulation` to completion on a fresh executor, reactor and [`SimulationPlatform`]
+/// seeded with `seed`, and returns its output. The random of the platform is installed as the
+/// source of [`crate::deterministic::random::sim_rng`] during the run.
+///
+/// ```rust
+/// use circus_simulation::prelude::*;
+/// use std::time::Duration;
+///
+/// let elapsed = circus_simulation::run(42, |platform| async move {
+///     let start = platform.now();
+///     platform.sleep(Duration::from_secs(60)).await;
+///     platform.now().duration_since(start)
+/// });
+/// assert_eq!(elapsed, Duration::from_secs(60));
+/// ```
+///
+/// # Panics
+/// Panics if the simulation did not complete, for example when it waits on a task that never
+/// wakes it up.
+pub fn run<F, Fut, T>(seed: u64, simulation: F) -> T
+where
+    F: FnOnce(SimulationPlatform) -> Fut,
+    Fut: Future<Output = T> + 'static,
+    T: 'static,
+{
+    run_on(
+        SimulationPlatform::new(seed, DeterministicReactor::default()),
+        simulation,
+    )
+}
+
+/// Like [`run`], on a `platform` created by the caller, such as a recording platform.
+/// The executor advances the reactor of the platform.
+pub(crate) fn run_on<F, Fut, T>(platform: SimulationPlatform, simulation: F) -> T
+where
+    F: FnOnce(SimulationPlatform) -> Fut,
+    Fut: Future<Output = T> + 'static,
+    T: 'static,
+{
+    let mut executor = DeterministicExecutor::new_with_reactor(platform.reactor());
+    let output = Rc::new(RefCell::new(None));
+
+    let _guard = platform.random().install();
+    let future = simulation(platform);
+    let result = output.clone();
+    executor.spawn(Task::new(async move {
+        *result.borrow_mut() = Some(future.await);
+    }));
+    executor.run();
+
+    let output = output.borrow_mut().take();
+    output.expect("simulation did not complete")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::platform::Platform;
+    use crate::run::run;
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_run() {
+        let (opened, elapsed) = run(42, |mut platform| async move {
+            let start = platform.now();
+            let opened = platform.open(Path::new("/etc/hosts")).await.is_ok();
+            (opened, platform.now().duration_since(start))
+        });
+        assert!(opened);
+        assert_eq!(elapsed, Duration::from_millis(817));
+    }
+}
@@ -0,0 +1,124 @@
+//! Seed search module
+use crate::deterministic::platform::SimulationPlatform;
+use crate::deterministic::runtime::executor::DeterministicExecutor;
+use crate::deterministic::runtime::reactor::DeterministicReactor;
+use crate::deterministic::runtime::task::Task;
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+
+/// How many seeds below a failing seed are tried while minimizing it.
+const SHRINK_WINDOW: u64 = 100;
+
+/// Summary of a successful [`run_search`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchReport {
+    /// number of seeds that ran without failure
+    pub passed: usize,
+}
+
+/// Summary of a failing [`run_search`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchFailure {
+    /// number of seeds that ran without failure before the first failure
+    pub passed: usize,
+    /// the first seed that failed
+    pub seed: u64,
+    /// the smallest failing seed found near `seed`
+    pub minimized_seed: u64,
+    /// the panic message of the failing run
+    pub message: String,
+}
+
+impl fmt::Display for SearchFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "simulation failed with seed {} (minimized to {}) after {} successful runs: {}",
+            self.seed, self.minimized_seed, self.passed, self.message
+        )
+    }
+}
+
+impl std::error::Error for SearchFailure {}
+
+/// Runs `simulation` under `iterations` random seeds, each time on a fresh executor, reactor
+/// and [`SimulationPlatform`], whose random is the source of
+/// [`crate::deterministic::random::sim_rng`] during the run. A simulation fails when it panics.
+/// On the first failure, seeds right below the failing one are tried to find a smaller
+/// failing seed, and the search stops.
+pub fn run_search<F, Fut>(iterations: usize, simulation: F) -> Result<SearchReport, SearchFailure>
+where
+    F: Fn(u64, SimulationPlatform) -> Fut,
+    Fut: Future<Output = ()> + 'static,
+{
+    for passed in 0..iterations {
+        let seed: u64 = rand::random();
+        if let Err(message) = run_seed(seed, &simulation) {
+            tracing::info!("seed {} failed: {}", seed, message);
+            let minimized_seed = (seed.saturating_sub(SHRINK_WINDOW)..seed)
+                .find(|candidate| run_seed(*candidate, &simulation).is_err())
+                .unwrap_or(seed);
+            return Err(SearchFailure {
+                passed,
+                seed,
+                minimized_seed,
+                message,
+            });
+        }
+    }
+    Ok(SearchReport { passed: iterations })
+}
+
+/// runs a single simulation, returning the panic message if it failed
+fn run_seed<F, Fut>(seed: u64, simulation: &F) -> Result<(), String>
+where
+    F: Fn(u64, SimulationPlatform) -> Fut,
+    Fut: Future<Output = ()> + 'static,
+{
+    let reactor = DeterministicReactor::default();
+    let mut executor = DeterministicExecutor::new_with_reactor(reactor.clone());
+    let platform = SimulationPlatform::new(seed, reactor);
+    let _guard = platform.random().install();
+    executor.spawn(Task::new(simulation(seed, platform)));
+
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        executor.run();
+    }))
+    .map_err(|payload| {
+        if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            String::from("unknown panic")
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::{run_search, SearchReport, SHRINK_WINDOW};
+
+    #[test]
+    fn test_search_success() {
+        let report = run_search(10, |_seed, _platform| async {});
+        assert_eq!(report, Ok(SearchReport { passed: 10 }));
+    }
+
+    #[test]
+    fn test_search_failure() {
+        let failure = run_search(1000, |seed, _platform| async move {
+            assert_ne!(seed % 4, 1, "unlucky seed");
+        })
+        .expect_err("a seed should have failed");
+
+        assert_eq!(failure.seed % 4, 1);
+        // the smallest failing seed within the shrinking window
+        let expected = (failure.seed.saturating_sub(SHRINK_WINDOW)..=failure.seed)
+            .find(|seed| seed % 4 == 1)
+            .unwrap();
+        assert_eq!(failure.minimized_seed, expected);
+        assert!(failure.message.contains("unlucky seed"));
+    }
+}
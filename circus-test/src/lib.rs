@@ -1,5 +1,7 @@
 #![warn(missing_docs)]
 #![warn(rust_2018_idioms)]
+// the examples are showing how to annotate tests
+#![allow(clippy::test_attr_in_doctest)]
 
 //! Allow injection of a random seed upon a test. Can be overloaded with environment var `DETERMINISTIC_SEED`.
 //!
@@ -13,6 +15,35 @@
 //!     println!("{}", seed);
 //! }
 //! ```
+//! ## With random seed, also seeding buggify:
+//! The global buggifier of `circus_buggify` is enabled with the seed for the duration of the test.
+//! ```rust
+//! use circus_test::with_random_seed;
+//!
+//! #[with_random_seed(buggify)]
+//! #[test]
+//! fn buggified_seed(seed: u64) {
+//!     assert!(circus_buggify::is_buggify_enabled());
+//! }
+//! ```
+//! ## With random seed, recording failing seeds:
+//! When the test panics, its seed is appended to the corpus file, one seed per line.
+//! The corpus can later be replayed with `#[with_seeds_from_file]`.
+//! ```rust
+//! use circus_test::{with_random_seed, with_seeds_from_file};
+//!
+//! #[with_random_seed(corpus = "target/regressions.seeds")]
+//! #[test]
+//! fn recorded_seed(seed: u64) {
+//!     println!("{}", seed);
+//! }
+//!
+//! #[with_seeds_from_file("target/regressions.seeds")]
+//! #[test]
+//! fn replayed_seeds(seed: u64) {
+//!     println!("{}", seed);
+//! }
+//! ```
 //! ## With fixed seed:
 //! ```rust
 //! use circus_test::with_seed;
@@ -23,26 +54,70 @@
 //!     println!("{}", seed);
 //! }
 //! ```
+//! ## Outside of tests:
+//! The seed is resolved the same way anywhere, such as in a fuzzing loop or a bench.
+//! ```rust
+//! use circus_test::resolve_seed;
+//!
+//! let seed: u64 = resolve_seed!();
+//! println!("{}", seed);
+//! ```
 
 use proc_macro::TokenStream;
 use syn::parse::{Parse, ParseStream};
-use syn::{ItemFn, LitInt};
+use syn::{Expr, Ident, ItemFn, Token};
 
 #[derive(Debug)]
 #[doc(hidden)]
 struct Seed {
-    value: Option<u64>,
+    value: Option<Expr>,
 }
 
 impl Parse for Seed {
     fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
-        let lit: LitInt = input.parse()?;
-        let value = lit.base10_parse::<u64>()?;
+        let value: Expr = input.parse()?;
         Ok(Seed { value: Some(value) })
     }
 }
 
+#[derive(Debug, Default)]
+#[doc(hidden)]
+struct RandomSeedOptions {
+    buggify: bool,
+    corpus: Option<Expr>,
+}
+
+impl Parse for RandomSeedOptions {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let mut options = RandomSeedOptions::default();
+        while !input.is_empty() {
+            let flag: Ident = input.parse()?;
+            if flag == "buggify" {
+                options.buggify = true;
+            } else if flag == "corpus" {
+                input.parse::<Token![=]>()?;
+                options.corpus = Some(input.parse()?);
+            } else {
+                return Err(syn::Error::new(
+                    flag.span(),
+                    "expected `buggify` or `corpus = \"path\"`",
+                ));
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(options)
+    }
+}
+
 /// Allow injection of a random seed upon a test. Can be overloaded with environment var `DETERMINISTIC_SEED`.
+/// With `#[with_random_seed(buggify)]`, the global buggifier of `circus_buggify` is enabled
+/// with the seed before the test, and disabled after it.
+/// With `#[with_random_seed(corpus = "path")]`, the seed of a panicking test is appended to the
+/// file at `path`, relative to the root of the crate of the test whatever the working
+/// directory, so that it can be replayed with
+/// [`macro@with_seeds_from_file`]. Options can be combined, separated by commas.
 ///
 /// ## Example:
 /// ```rust
@@ -55,12 +130,14 @@ impl Parse for Seed {
 /// }
 /// ```
 #[proc_macro_attribute]
-pub fn with_random_seed(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn with_random_seed(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let options = syn::parse_macro_input!(attr as RandomSeedOptions);
     let input = syn::parse_macro_input!(item as ItemFn);
-    wrap_test_function(&input, None)
+    wrap_test_function(&input, None, &options)
 }
 
-/// Allow injection of a fixed seed upon a test.
+/// Allow injection of a fixed seed upon a test. The seed can be any expression evaluating to an `u64`,
+/// such as a literal or a constant.
 ///
 /// ## Example:
 /// ```rust
@@ -71,30 +148,168 @@ pub fn with_random_seed(_attr: TokenStream, item: TokenStream) -> TokenStream {
 /// fn random_seed(seed: u64) {
 ///     assert_eq!(42, seed);
 /// }
+///
+/// const CURATED_SEED: u64 = 1337;
+///
+/// #[with_seed(CURATED_SEED)]
+/// #[test]
+/// fn curated_seed(seed: u64) {
+///     assert_eq!(CURATED_SEED, seed);
+/// }
 /// ```
 #[proc_macro_attribute]
 pub fn with_seed(attr: TokenStream, item: TokenStream) -> TokenStream {
     let attributes = syn::parse_macro_input!(attr as Seed);
     let input = syn::parse_macro_input!(item as ItemFn);
 
-    wrap_test_function(&input, attributes.value)
+    wrap_test_function(&input, attributes.value, &RandomSeedOptions::default())
 }
 
-fn wrap_test_function(input: &ItemFn, seed: Option<u64>) -> TokenStream {
+/// Evaluates to the seed given by the environment var `DETERMINISTIC_SEED`, or to a random
+/// seed if it is not set, like [`macro@with_random_seed`] does. Useful outside of `#[test]`,
+/// such as in a `main` running a fuzzing loop, or in a bench. Like the attributes, the expansion
+/// is using `rand`, which must be a dependency of the calling crate.
+///
+/// # Panics
+/// Panics if `DETERMINISTIC_SEED` is set but is not an `u64`.
+///
+/// ## Example:
+/// ```rust
+/// use circus_test::resolve_seed;
+///
+/// std::env::set_var("DETERMINISTIC_SEED", "42");
+/// assert_eq!(resolve_seed!(), 42);
+/// ```
+#[proc_macro]
+pub fn resolve_seed(input: TokenStream) -> TokenStream {
+    syn::parse_macro_input!(input as syn::parse::Nothing);
+    let resolve_seed = resolve_seed_expr();
+    quote::quote!(#resolve_seed).into()
+}
+
+/// the expression resolving the seed from `DETERMINISTIC_SEED`, or drawing one
+fn resolve_seed_expr() -> impl quote::ToTokens {
+    quote::quote! {
+        match std::env::var("DETERMINISTIC_SEED") {
+            Ok(val) => match val.parse::<u64>() {
+                Ok(seed) => seed,
+                Err(e) => panic!("could not parse '{}' as an u64: {}", val, e),
+            },
+            Err(_) => rand::random::<u64>(),
+        }
+    }
+}
+
+/// Runs a test once for every seed listed in a file, one seed per line. Empty lines and lines
+/// starting with `#` are skipped, and a missing file is an empty corpus. The path is relative
+/// to the root of the crate of the test whatever the working directory, and is typically a corpus written by `#[with_random_seed(corpus = "path")]`.
+///
+/// ## Example:
+/// ```rust
+/// use circus_test::with_seeds_from_file;
+///
+/// #[with_seeds_from_file("target/regressions.seeds")]
+/// #[test]
+/// fn replayed_seeds(seed: u64) {
+///     println!("{}", seed);
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn with_seeds_from_file(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let path = syn::parse_macro_input!(attr as Expr);
+    let input = syn::parse_macro_input!(item as ItemFn);
+    let fn_name = &input.sig.ident;
+    let attrs = &input.attrs;
+    let test_block = &input.block;
+
+    quote::quote!(
+        #(#attrs)*
+        fn #fn_name() {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(#path);
+            let corpus = match std::fs::read_to_string(&path) {
+                Ok(corpus) => corpus,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => panic!("could not read seeds from '{}': {}", path.display(), e),
+            };
+            let run = |seed: u64| #test_block;
+            for line in corpus.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                match line.parse::<u64>() {
+                    Ok(seed) => run(seed),
+                    Err(e) => panic!("could not parse '{}' as an u64: {}", line, e),
+                }
+            }
+        }
+    )
+    .into()
+}
+
+fn wrap_test_function(
+    input: &ItemFn,
+    seed: Option<Expr>,
+    options: &RandomSeedOptions,
+) -> TokenStream {
     let fn_name = &input.sig.ident;
-    let block = &input.block;
     let attrs = &input.attrs;
+    let test_block = &input.block;
+
+    let block = if options.buggify {
+        quote::quote! {
+            {
+                // disabling buggify even if the test is panicking
+                struct DisableBuggify;
+                impl Drop for DisableBuggify {
+                    fn drop(&mut self) {
+                        circus_buggify::disable_buggify();
+                    }
+                }
+
+                circus_buggify::enable_buggify_with_seed(seed);
+                let _disable_buggify = DisableBuggify;
+                #test_block
+            }
+        }
+    } else {
+        quote::quote!(#test_block)
+    };
+
+    let block = match &options.corpus {
+        Some(corpus) => quote::quote! {
+            {
+                // recording the seed when the test is panicking
+                struct RecordFailingSeed(u64);
+                impl Drop for RecordFailingSeed {
+                    fn drop(&mut self) {
+                        if std::thread::panicking() {
+                            use std::io::Write;
+                            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(#corpus);
+                            let recorded = std::fs::OpenOptions::new()
+                                .create(true)
+                                .append(true)
+                                .open(&path)
+                                .and_then(|mut file| writeln!(file, "{}", self.0));
+                            if let Err(e) = recorded {
+                                let path = path.display();
+                                eprintln!("could not record seed {} in '{}': {}", self.0, path, e);
+                            }
+                        }
+                    }
+                }
+
+                let _record_failing_seed = RecordFailingSeed(seed);
+                #block
+            }
+        },
+        None => block,
+    };
 
     let body = match seed {
         None => {
+            let resolve_seed = resolve_seed_expr();
             quote::quote! {
-                let seed: u64 = match std::env::var("DETERMINISTIC_SEED") {
-                    Ok(val) => match val.parse::<u64>() {
-                        Ok(seed) => seed,
-                        Err(e) => panic!("could not parse '{}' as an u64: {}", val, e),
-                    },
-                    Err(_) => rand::random(),
-                };
+                let seed: u64 = #resolve_seed;
                 #block
 
             }
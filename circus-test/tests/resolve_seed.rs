@@ -0,0 +1,19 @@
+use circus_test::resolve_seed;
+
+// a single test in its own binary, as it is setting the environment var read by every seeded test
+#[test]
+fn resolve_seed_from_env() {
+    std::env::set_var("DETERMINISTIC_SEED", "42");
+    assert_eq!(resolve_seed!(), 42);
+
+    std::env::set_var("DETERMINISTIC_SEED", "forty-two");
+    let invalid = std::panic::catch_unwind(|| resolve_seed!());
+    assert!(invalid.is_err(), "an invalid seed should panic");
+
+    std::env::remove_var("DETERMINISTIC_SEED");
+    let seeds: Vec<u64> = (0..10).map(|_| resolve_seed!()).collect();
+    assert!(
+        seeds.iter().any(|seed| *seed != seeds[0]),
+        "seeds are random"
+    );
+}
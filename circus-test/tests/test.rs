@@ -1,5 +1,9 @@
 use circus_test::with_random_seed;
 use circus_test::with_seed;
+use circus_test::with_seeds_from_file;
+use std::cell::RefCell;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
 
 #[with_random_seed]
 #[test]
@@ -7,15 +11,107 @@ fn random_seed(seed: u64) {
     println!("{}", seed);
 }
 
+#[with_random_seed(buggify)]
+#[test]
+fn random_seed_with_buggify(_seed: u64) {
+    assert!(circus_buggify::is_buggify_enabled());
+    assert!(circus_buggify::buggify_with_prob(1.0));
+}
+
 #[with_seed(42)]
 #[test]
 fn with_seed(seed: u64) {
     assert_eq!(42, seed);
 }
 
+const CURATED_SEED: u64 = 1337;
+
+#[with_seed(CURATED_SEED)]
+#[test]
+fn with_const_seed(seed: u64) {
+    assert_eq!(CURATED_SEED, seed);
+}
+
 #[with_random_seed]
 #[test]
 #[ignore]
 fn ignored_test(_seed: u64) {
     unreachable!("test should not be executed");
 }
+
+const CORPUS: &str = concat!(env!("CARGO_TARGET_TMPDIR"), "/corpus.seeds");
+
+thread_local! {
+    static REPLAYED: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+}
+
+#[with_random_seed(corpus = CORPUS)]
+fn failing_simulation(seed: u64) {
+    panic!("simulated failure with seed {}", seed);
+}
+
+#[with_seeds_from_file(CORPUS)]
+fn replay_corpus(seed: u64) {
+    REPLAYED.with(|replayed| replayed.borrow_mut().push(seed));
+}
+
+#[test]
+fn random_seed_with_corpus() {
+    let _ = std::fs::remove_file(CORPUS);
+
+    assert!(std::panic::catch_unwind(failing_simulation).is_err());
+    assert!(std::panic::catch_unwind(failing_simulation).is_err());
+
+    let recorded: Vec<u64> = std::fs::read_to_string(CORPUS)
+        .expect("the failing seeds should be recorded")
+        .lines()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    assert_eq!(recorded.len(), 2);
+
+    replay_corpus();
+    REPLAYED.with(|replayed| assert_eq!(*replayed.borrow(), recorded));
+}
+
+/// returns the path of a corpus in the target directory, relative to the root of the crate
+fn relative_corpus() -> PathBuf {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let corpus = Path::new(env!("CARGO_TARGET_TMPDIR")).join("relative.seeds");
+    let common = root
+        .components()
+        .zip(corpus.components())
+        .take_while(|(root, corpus)| root == corpus)
+        .count();
+    let up = root.components().skip(common).map(|_| Component::ParentDir);
+    up.chain(corpus.components().skip(common)).collect()
+}
+
+#[with_random_seed(corpus = relative_corpus())]
+fn failing_relative_simulation(seed: u64) {
+    panic!("simulated failure with seed {}", seed);
+}
+
+#[test]
+fn relative_corpus_ignores_working_directory() {
+    // the child process is only running the failing simulation
+    if std::env::var_os("CIRCUS_TEST_RELATIVE_CORPUS").is_some() {
+        failing_relative_simulation();
+        return;
+    }
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(relative_corpus());
+    let _ = std::fs::remove_file(&path);
+
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["relative_corpus_ignores_working_directory", "--exact"])
+        .env("CIRCUS_TEST_RELATIVE_CORPUS", "1")
+        .current_dir(env!("CARGO_TARGET_TMPDIR"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .expect("could not run the failing simulation");
+    assert!(!status.success());
+
+    let recorded = std::fs::read_to_string(&path).expect("the failing seed should be recorded");
+    assert_eq!(recorded.lines().count(), 1);
+}